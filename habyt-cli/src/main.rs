@@ -2,12 +2,58 @@ extern crate lib;
 #[macro_use]
 extern crate clap;
 
-use clap::{Arg, SubCommand};
-use lib::{HabitDraft, HabitId, HabitLog, HabitPatch, HabitStoreFs};
+use chrono::{Datelike, Local, Utc};
+use clap::{AppSettings, Arg, ArgMatches, SubCommand};
+use lib::{
+    bury_orphans, compute_streak, create_data_dir, data_dir, export_csv, export_ics, habit_stats,
+    heatmap, import_csv, import_loop, is_due_today, journal_entries, orphaned_logs, parse_date,
+    parse_schedule, progress, quantum_by_period, render_journal, validate_range, write_records,
+    BackupRecord, DeletePolicy, ExportRecord, Habit, HabitDraft, HabitId, HabitLog, HabitLogPatch,
+    HabitPatch, HabitRecord, HabitStore, HabitStoreFs, HabitStoreSqlite, HabytError, HabytResult,
+    HasPosNeg, HeatmapDayRecord, HeatmapWeek, ImportRecord, Kind, LogRecord, OutputFormat, Period,
+    Progress, ProgressRecord, ProgressStatus, Schedule, Shade, StatsRecord, StorageBackend, Trend,
+    DEFAULT_LOCK_TIMEOUT,
+};
+use std::fs::File;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::process;
 
 fn main() {
     let matches = app_from_crate!()
-        .template("{bin} {version}\n{author}\n\n{about}\n\nUSAGE:\n    {usage}\n\nFLAGS:\n{flags}\n\nSUBCOMMANDS:\n{subcommands}")
+        .template("{bin} {version}\n{author}\n\n{about}\n\nUSAGE:\n    {usage}\n\nFLAGS:\n{flags}\n\nOPTIONS:\n{options}\n\nSUBCOMMANDS:\n{subcommands}")
+        .arg(
+            Arg::with_name("backend")
+                .long("backend")
+                .global(true)
+                .takes_value(true)
+                .possible_values(&["yaml", "sqlite"])
+                .default_value("yaml")
+                .help("Where habits and logs are kept")
+            )
+        .arg(
+            Arg::with_name("data-dir")
+                .long("data-dir")
+                .global(true)
+                .takes_value(true)
+                .help("The directory to keep habits in, instead of $HABYT_HOME or the default one")
+            )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .global(true)
+                .takes_value(true)
+                .possible_values(&["table", "json", "yaml", "csv"])
+                .default_value("table")
+                .help("How to write the output, table for sentences or json, yaml or csv for scripts")
+            )
+        .arg(
+            Arg::with_name("profile")
+                .long("profile")
+                .global(true)
+                .takes_value(true)
+                .help("A separate set of habits, e.g. work or personal")
+            )
         .subcommand(
             SubCommand::with_name("add")
                 .about("Add a new habit")
@@ -24,6 +70,8 @@ fn main() {
                         .required(true)
                     )
                 .arg_from_usage("-u, --unit=[unit] 'A measurable unit. E.g. words'")
+                .arg_from_usage("--negative 'Treat the goal as a limit to stay under. E.g. cigarettes'")
+                .arg_from_usage("-s, --schedule=[schedule] 'When the habit is due. E.g. daily, weekly, mon,wed,fri, 3/week or \"every 2 days\"'")
         )
         .subcommand(
             SubCommand::with_name("upd")
@@ -36,6 +84,7 @@ fn main() {
                     )
                 .arg_from_usage("-n, --name=[name] 'A one or two word name for the habit. E.g. writing'")
                 .arg_from_usage("-q, --quantum=[quantum] 'A daily goal. E.g. 750'")
+                .arg_from_usage("-s, --schedule=[schedule] 'When the habit is due. E.g. daily, weekly, mon,wed,fri, 3/week or \"every 2 days\"'")
         )
         .subcommand(
             SubCommand::with_name("del")
@@ -46,6 +95,14 @@ fn main() {
                         .help("'id' of the habit you want to delete . E.g. 1")
                         .required(true)
                     )
+                .arg(
+                    Arg::with_name("logs")
+                        .long("logs")
+                        .takes_value(true)
+                        .possible_values(&["cascade", "tombstone", "archive"])
+                        .default_value("archive")
+                        .help("Whether to delete the habit's logs, keep them, or archive them with it")
+                    )
         )
         .subcommand(
             SubCommand::with_name("log")
@@ -58,105 +115,1123 @@ fn main() {
                     )
                 .arg_from_usage("-q, --quantum=[quantum] 'The goal youve been able to achieve today. E.g. 750'")
                 .arg_from_usage("-n, --notes=[notes] 'Any accompanying thoughts youd like to add.'")
+                .arg(
+                    Arg::with_name("date")
+                        .short("d")
+                        .long("date")
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                        .help("The day you did it, if not today. E.g. 2021-06-01, yesterday or -3d")
+                    )
+                .arg_from_usage("--force 'Allow logging dates in the future or before the habit was created'")
+                .setting(AppSettings::SubcommandsNegateReqs)
+                .subcommand(
+                    SubCommand::with_name("edit")
+                        .about("Correct a log entry")
+                        .arg(
+                            Arg::with_name("log-id")
+                                .index(1)
+                                .help("'id' of the log entry, as shown by llist. E.g. 12")
+                                .required(true)
+                            )
+                        .arg_from_usage("-q, --quantum=[quantum] 'What you actually achieved. E.g. 750'")
+                        .arg_from_usage("-n, --notes=[notes] 'Any accompanying thoughts youd like to add.'")
+                        .arg(
+                            Arg::with_name("date")
+                                .short("d")
+                                .long("date")
+                                .takes_value(true)
+                                .allow_hyphen_values(true)
+                                .help("The day you actually did it. E.g. 2021-06-01, yesterday or -3d")
+                            )
+                        .arg_from_usage("--force 'Allow dates in the future or before the habit was created'")
+                )
+                .subcommand(
+                    SubCommand::with_name("rm")
+                        .about("Remove a log entry")
+                        .arg(
+                            Arg::with_name("log-id")
+                                .index(1)
+                                .help("'id' of the log entry, as shown by llist. E.g. 12")
+                                .required(true)
+                            )
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("pause")
+                .about("Set a habit aside, e.g. for a vacation, without breaking its streak")
+                .arg(
+                    Arg::with_name("id")
+                        .index(1)
+                        .help("'id' of the habit you want to pause . E.g. 1")
+                        .required(true)
+                    )
+                .arg(
+                    Arg::with_name("until")
+                        .long("until")
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                        .help("The last day of the pause, if you know it. E.g. 2021-06-14 or +7d")
+                    )
+        )
+        .subcommand(
+            SubCommand::with_name("resume")
+                .about("Pick a paused habit back up from today")
+                .arg(
+                    Arg::with_name("id")
+                        .index(1)
+                        .help("'id' of the habit you want to resume . E.g. 1")
+                        .required(true)
+                    )
         )
         .subcommand(SubCommand::with_name("list").about("List all the habits"))
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("Show how a habit went over a range of days")
+                .arg(
+                    Arg::with_name("id")
+                        .index(1)
+                        .help("'id' of the habit you want statistics for . E.g. 1")
+                        .required(true)
+                    )
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                        .help("The first day to look at, instead of when tracking began. E.g. 2021-06-01 or -4w")
+                    )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                        .help("The last day to look at, instead of today. E.g. 2021-06-30 or yesterday")
+                    )
+        )
+        .subcommand(
+            SubCommand::with_name("heatmap")
+                .about("Show a year of a habit, day by day")
+                .arg(
+                    Arg::with_name("id")
+                        .index(1)
+                        .help("'id' of the habit you want to see. E.g. 1")
+                        .required(true)
+                    )
+                .arg_from_usage("--no-color 'Draw with plain characters, also done when NO_COLOR is set or output is not a terminal'")
+        )
+        .subcommand(
+            SubCommand::with_name("journal")
+                .about("Write the log notes as a Markdown journal, a section a day")
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                        .help("The first day to write about. E.g. 2021-06-01 or -4w")
+                    )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                        .help("The last day to write about. E.g. 2021-06-30 or yesterday")
+                    )
+                .arg(
+                    Arg::with_name("habit")
+                        .long("habit")
+                        .takes_value(true)
+                        .value_name("id")
+                        .help("Only write about the habit with this 'id'. E.g. 1")
+                    )
+        )
+        .subcommand(SubCommand::with_name("today").about("Show what's due today and how far along it is"))
         .subcommand(SubCommand::with_name("llist").about("List all the log"))
+        .subcommand(
+            SubCommand::with_name("restore")
+                .about("Bring back an archived habit, roll back to a backup, or list the backups")
+                .arg(
+                    Arg::with_name("id")
+                        .index(1)
+                        .conflicts_with("backup")
+                        .help("'id' of the archived habit you want back. E.g. 1")
+                    )
+                .arg_from_usage("-b, --backup=[backup] 'The backup to roll back to. E.g. 2021-06-01T093000.000'")
+        )
+        .subcommand(
+            SubCommand::with_name("archive")
+                .about("Look into the archive of deleted habits")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(SubCommand::with_name("list").about("List the archived habits"))
+        )
+        .subcommand(
+            SubCommand::with_name("doctor")
+                .about("Look for log entries of habits that are gone")
+                .arg_from_usage("--fix 'Keep the orphaned log entries under a tombstone of their habit'")
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Export the habits and logs for other tools")
+                .arg(
+                    Arg::with_name("csv")
+                        .long("csv")
+                        .takes_value(true)
+                        .value_name("dir")
+                        .required_unless("ics")
+                        .help("Write habits.csv and logs.csv to this directory")
+                    )
+                .arg(
+                    Arg::with_name("ics")
+                        .long("ics")
+                        .takes_value(true)
+                        .value_name("file")
+                        .help("Write the habits and the days they were done to this iCalendar file. E.g. habits.ics")
+                    )
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Import habits or logs from other tools")
+                .arg(
+                    Arg::with_name("csv")
+                        .long("csv")
+                        .takes_value(true)
+                        .value_name("file")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .required_unless("loop")
+                        .help("A CSV file of habits or of logs, as written by export. Give habits before their logs")
+                    )
+                .arg(
+                    Arg::with_name("loop")
+                        .long("loop")
+                        .takes_value(true)
+                        .value_name("dir")
+                        .help("An unzipped Loop Habit Tracker export, with Habits.csv in it")
+                    )
+                .arg_from_usage("--dry-run 'Report what would be imported without saving it'")
+        )
+        .subcommand(
+            SubCommand::with_name("import-yaml")
+                .about("Import the YAML habits and logs into the SQLite store")
+        )
         .get_matches();
 
+    if let Err(e) = run(&matches) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(matches: &ArgMatches) -> HabytResult<()> {
     //
-    let mut habitctl = HabitStoreFs::new();
-    habitctl.load();
+    let data_dir = data_dir(
+        matches.value_of_os("data-dir").map(Path::new),
+        matches.value_of("profile"),
+    )?;
+    if create_data_dir(&data_dir)? {
+        eprintln!("Welcome to Habyt!\n");
+    }
+    let mut habitctl: Box<dyn StorageBackend> = match matches.value_of("backend") {
+        Some("sqlite") => Box::new(HabitStoreSqlite::open_in(&data_dir)?),
+        _ => {
+            let store_fs = HabitStoreFs::open(&data_dir)?;
+            if let Some(store_file) = store_fs.created() {
+                eprintln!(
+                    "Created {}. This file will list your currently tracked habits.",
+                    store_file.display()
+                );
+            }
+            Box::new(store_fs)
+        }
+    };
+    // Held until we're done, so another habyt can't slip its changes in between
+    let _lock = habitctl.lock(DEFAULT_LOCK_TIMEOUT)?;
+    let mut store = habitctl.load()?;
+    let format: OutputFormat = matches.value_of("format").unwrap().parse()?;
 
     //
     match matches.subcommand() {
         ("add", Some(sub_matches)) => {
             let name = String::from(sub_matches.value_of("name").unwrap());
-            let quantum = value_t!(sub_matches, "quantum", f64).unwrap();
+            let quantum = value_t!(sub_matches, "quantum", f64).unwrap_or_else(|e| e.exit());
             let unit = String::from(sub_matches.value_of("unit").unwrap_or(""));
+            let kind = if sub_matches.is_present("negative") {
+                Kind::Negative
+            } else {
+                Kind::Positive
+            };
 
-            let habit_id: HabitId =
-                habitctl
-                    .store
-                    .create(HabitDraft::new(name.clone(), quantum, unit.clone()));
+            let mut draft = HabitDraft::try_from(
+                name.clone(),
+                quantum,
+                unit.clone(),
+                None,
+                None,
+                Some(kind),
+                None,
+            )?;
+            if let Some(schedule) = sub_matches.value_of("schedule") {
+                let (streak, schedule) = parse_schedule(schedule, Local::today().naive_local())?;
+                draft = draft.with_schedule(streak, schedule);
+            }
+            let habit_id: HabitId = store.create(draft)?;
 
-            habitctl.save();
+            habitctl.save(&store)?;
+            if format != OutputFormat::Table {
+                let habit = store.get(habit_id).unwrap();
+                return write_records(
+                    &[HabitRecord::new(habit, Local::today().naive_local())],
+                    format,
+                    io::stdout(),
+                );
+            }
 
             println!(
-                "You have commited to {} {} of {} ({}) every day!",
-                &quantum, &unit, &name, habit_id
+                "You have commited to {} ({}), {}!",
+                &name,
+                habit_id,
+                describe_goal(store.get(habit_id).unwrap())
             );
         }
         ("upd", Some(sub_matches)) => {
-            let id: u32 = String::from(sub_matches.value_of("id").unwrap())
-                .parse::<u32>()
-                .unwrap();
-
-            habitctl.store.update(
-                id,
-                HabitPatch::from(
-                    String::from(sub_matches.value_of("name").unwrap_or("")),
-                    value_t!(sub_matches, "quantum", f64).unwrap_or(0.),
-                    String::from(sub_matches.value_of("unit").unwrap_or("")),
-                    None,
-                    None,
-                    None,
-                    None,
-                ),
-            );
+            let id: u32 = value_t!(sub_matches, "id", u32).unwrap_or_else(|e| e.exit());
+
+            let mut patch = HabitPatch::try_from(
+                String::from(sub_matches.value_of("name").unwrap_or("")),
+                value_t!(sub_matches, "quantum", f64).unwrap_or(0.),
+                String::from(sub_matches.value_of("unit").unwrap_or("")),
+                None,
+                None,
+                None,
+                None,
+            )?;
+            if let Some(schedule) = sub_matches.value_of("schedule") {
+                let (streak, schedule) = parse_schedule(schedule, Local::today().naive_local())?;
+                patch = patch.with_schedule(streak, schedule);
+            }
+            store.update(id, patch)?;
 
-            habitctl.save();
+            habitctl.save(&store)?;
+            if format != OutputFormat::Table {
+                let habit = store.get(id).unwrap();
+                return write_records(
+                    &[HabitRecord::new(habit, Local::today().naive_local())],
+                    format,
+                    io::stdout(),
+                );
+            }
         }
         ("del", Some(sub_matches)) => {
-            let id: u32 = String::from(sub_matches.value_of("id").unwrap())
-                .parse::<u32>()
-                .unwrap();
-            if let Some(habit) = habitctl.store.delete(id) {
-                habitctl.save();
-                println!(
-                    "Habyt is not tracking {} with id {} anymore!",
-                    &habit.0.name, &habit.0.id
+            let id: u32 = value_t!(sub_matches, "id", u32).unwrap_or_else(|e| e.exit());
+            let policy: DeletePolicy = sub_matches.value_of("logs").unwrap().parse()?;
+            let mut store_log = habitctl.load_log()?;
+            let habit = store
+                .delete_with(id, policy, &mut store_log)
+                .ok_or_else(|| HabytError::NotFound(format!("There's no habit with id {}", id)))?;
+            habitctl.commit(&store, &store_log)?;
+            if format != OutputFormat::Table {
+                return write_records(
+                    &[HabitRecord::new(&habit.0, Local::today().naive_local())],
+                    format,
+                    io::stdout(),
                 );
             }
+            println!(
+                "Habyt is not tracking {} with id {} anymore!",
+                &habit.0.name, &habit.0.id
+            );
+        }
+        ("pause", Some(sub_matches)) => {
+            let id: u32 = value_t!(sub_matches, "id", u32).unwrap_or_else(|e| e.exit());
+            let today = Local::today().naive_local();
+            let until = match sub_matches.value_of("until") {
+                Some(until) => Some(parse_date(until, today)?),
+                None => None,
+            };
+
+            store.pause(id, today, until)?;
+            habitctl.save(&store)?;
+            if format != OutputFormat::Table {
+                let habit = store.get(id).unwrap();
+                return write_records(&[HabitRecord::new(habit, today)], format, io::stdout());
+            }
+            let name = &store.get(id).unwrap().name;
+            match until {
+                Some(until) => println!("{} is paused until {}, enjoy the break!", name, until),
+                None => println!("{} is paused until you resume it, enjoy the break!", name),
+            }
+        }
+        ("resume", Some(sub_matches)) => {
+            let id: u32 = value_t!(sub_matches, "id", u32).unwrap_or_else(|e| e.exit());
+            let today = Local::today().naive_local();
+            store.resume(id, today)?;
+            habitctl.save(&store)?;
+            if format != OutputFormat::Table {
+                let habit = store.get(id).unwrap();
+                return write_records(&[HabitRecord::new(habit, today)], format, io::stdout());
+            }
+            println!("Welcome back to {}!", store.get(id).unwrap().name);
         }
         ("list", Some(_)) => {
-            let habits = habitctl.store.list();
+            let habits = store.list();
+            let store_log = habitctl.load_log()?;
+            let today = Local::today().naive_local();
+            if format != OutputFormat::Table {
+                let mut records: Vec<HabitRecord> = habits
+                    .iter()
+                    .map(|habit| {
+                        let streak =
+                            compute_streak(habit, &store_log.list_by_habit(habit.id), today);
+                        HabitRecord::new(habit, today).with_streak(&streak)
+                    })
+                    .collect();
+                records.sort_by_key(|record| record.id);
+                return write_records(&records, format, io::stdout());
+            }
 
             println!("You've commited to {} habits so far...", habits.len());
             for habit in habits.iter() {
+                let streak = compute_streak(habit, &store_log.list_by_habit(habit.id), today);
                 println!(
-                    "-> {} for {} {} a day \n",
-                    habit.name, habit.quantum, habit.unit
+                    "-> {} for {} (current streak: {}, longest: {}){} \n",
+                    habit.name,
+                    describe_goal(habit),
+                    streak.current,
+                    streak.longest,
+                    if habit.is_paused_on(today) {
+                        " [paused]"
+                    } else {
+                        ""
+                    }
                 );
             }
         }
-        ("log", Some(sub_matches)) => {
-            let id: u32 = String::from(sub_matches.value_of("id").unwrap())
-                .parse::<u32>()
-                .unwrap();
+        ("stats", Some(sub_matches)) => {
+            let id: u32 = value_t!(sub_matches, "id", u32).unwrap_or_else(|e| e.exit());
+            let habit = store
+                .get(id)
+                .ok_or_else(|| HabytError::NotFound(format!("There's no habit with id {}", id)))?;
+            let store_log = habitctl.load_log()?;
+            let logs = store_log.list_by_habit(id);
 
-            habitctl.load_log();
-            habitctl.store_log.add(HabitLog::new(
-                id,
-                value_t!(sub_matches, "quantum", f64).unwrap_or(0.),
-                Some(String::from(sub_matches.value_of("notes").unwrap_or(""))),
-            ));
-            habitctl.save_log();
+            let today = Local::today().naive_local();
+            let to = match sub_matches.value_of("to") {
+                Some(to) => parse_date(to, today)?,
+                None => today,
+            };
+            let from = match sub_matches.value_of("from") {
+                Some(from) => parse_date(from, today)?,
+                None => habit
+                    .tracked_since()
+                    .into_iter()
+                    .chain(logs.iter().map(|log_entry| log_entry.date))
+                    .min()
+                    .unwrap_or(today),
+            };
+            validate_range(from, to)?;
+
+            let stats = habit_stats(habit, &logs, from, to);
+            if format != OutputFormat::Table {
+                return write_records(&[StatsRecord::new(habit, &stats)], format, io::stdout());
+            }
+            let noun = habit.streak.noun();
+            println!(
+                "{} ({}) from {} to {}, going for {}...",
+                habit.name,
+                habit.id,
+                stats.range.start,
+                stats.range.end,
+                describe_goal(habit)
+            );
+            println!(
+                "-> completed {} of {} {}s ({:.0}%)",
+                stats.completed,
+                stats.periods,
+                noun,
+                stats.completion_rate * 100.
+            );
+            println!(
+                "-> {} {} in total, {:.1} a {} on average",
+                stats.total_quantum, habit.unit, stats.average_per_period, noun
+            );
+            if let (Some((best, best_quantum)), Some((worst, worst_quantum))) =
+                (stats.best_week, stats.worst_week)
+            {
+                println!(
+                    "-> best week: {} with {} {}",
+                    best.start, best_quantum, habit.unit
+                );
+                println!(
+                    "-> worst week: {} with {} {}",
+                    worst.start, worst_quantum, habit.unit
+                );
+            }
+            match stats.longest_gap {
+                Some(gap) => println!(
+                    "-> longest gap: {} days, from {} to {}",
+                    (gap.end - gap.start).num_days() + 1,
+                    gap.start,
+                    gap.end
+                ),
+                None => println!("-> no gaps, something was logged every day"),
+            }
+            println!(
+                "-> trend: {}",
+                match stats.trend {
+                    Trend::Rising => "rising",
+                    Trend::Steady => "steady",
+                    Trend::Falling => "falling",
+                }
+            );
+        }
+        ("heatmap", Some(sub_matches)) => {
+            let id: u32 = value_t!(sub_matches, "id", u32).unwrap_or_else(|e| e.exit());
+            let habit = store
+                .get(id)
+                .ok_or_else(|| HabytError::NotFound(format!("There's no habit with id {}", id)))?;
+            let store_log = habitctl.load_log()?;
+            let logs = store_log.list_by_habit(id);
+            let colors = !sub_matches.is_present("no-color")
+                && std::env::var_os("NO_COLOR").is_none()
+                && std::io::stdout().is_terminal();
+
+            let weeks = heatmap(habit, &logs, Local::today().naive_local(), HEATMAP_WEEKS);
+            if format != OutputFormat::Table {
+                return write_records(&HeatmapDayRecord::from_weeks(&weeks), format, io::stdout());
+            }
+            println!(
+                "{} ({}), going for {}...\n",
+                habit.name,
+                habit.id,
+                describe_goal(habit)
+            );
+            print!("{}", render_heatmap(&weeks, colors));
         }
+        ("journal", Some(sub_matches)) => {
+            let today = Local::today().naive_local();
+            let from = match sub_matches.value_of("from") {
+                Some(from) => Some(parse_date(from, today)?),
+                None => None,
+            };
+            let to = match sub_matches.value_of("to") {
+                Some(to) => Some(parse_date(to, today)?),
+                None => None,
+            };
+            if let (Some(from), Some(to)) = (from, to) {
+                validate_range(from, to)?;
+            }
+            let habit_id = sub_matches
+                .value_of("habit")
+                .map(|_| value_t!(sub_matches, "habit", u32).unwrap_or_else(|e| e.exit()));
+            if let Some(id) = habit_id {
+                if store.get(id).is_none() && store.tombstone(id).is_none() {
+                    return Err(HabytError::NotFound(format!(
+                        "There's no habit with id {}",
+                        id
+                    )));
+                }
+            }
+
+            let store_log = habitctl.load_log()?;
+            let entries = journal_entries(&store_log, from, to, habit_id);
+            if format != OutputFormat::Table {
+                let records: Vec<LogRecord> = entries
+                    .iter()
+                    .map(|log_entry| LogRecord::new(log_entry, store.name_of(log_entry.habit_id)))
+                    .collect();
+                return write_records(&records, format, io::stdout());
+            }
+            print!("{}", render_journal(&store, &entries));
+        }
+        ("today", Some(_)) => {
+            let store_log = habitctl.load_log()?;
+            let today = Local::today().naive_local();
+            let mut habits: Vec<&Habit> = store
+                .list()
+                .into_iter()
+                .filter(|habit| is_due_today(habit, today))
+                .collect();
+            habits.sort_by_key(|habit| habit.id);
+            if format != OutputFormat::Table {
+                let records: Vec<ProgressRecord> = habits
+                    .iter()
+                    .map(|habit| {
+                        let logs = store_log.list_by_habit(habit.id);
+                        ProgressRecord::new(
+                            habit,
+                            &progress(habit, &logs, today),
+                            &compute_streak(habit, &logs, today),
+                        )
+                    })
+                    .collect();
+                return write_records(&records, format, io::stdout());
+            }
+
+            println!("You have {} habits due today...", habits.len());
+            for habit in habits.iter() {
+                let logs = store_log.list_by_habit(habit.id);
+                let progress = progress(habit, &logs, today);
+                let streak = compute_streak(habit, &logs, today);
+                println!(
+                    "-> [{}] {} ({}): {} (current streak: {}) \n",
+                    match progress.status {
+                        ProgressStatus::Done => "done",
+                        ProgressStatus::Pending => "pending",
+                        ProgressStatus::UnderLimit => "under the limit",
+                        ProgressStatus::OverLimit => "over the limit!",
+                    },
+                    habit.name,
+                    habit.id,
+                    describe_progress(habit, &progress),
+                    streak.current
+                );
+            }
+        }
+        ("log", Some(log_matches)) => match log_matches.subcommand() {
+            ("edit", Some(sub_matches)) => {
+                let log_id: u32 = value_t!(sub_matches, "log-id", u32).unwrap_or_else(|e| e.exit());
+                let mut store_log = habitctl.load_log()?;
+                let log_entry = store_log.get(log_id).cloned().ok_or_else(|| {
+                    HabytError::NotFound(format!("There's no log entry with id {}", log_id))
+                })?;
+
+                let today = Local::today().naive_local();
+                let date = match sub_matches.value_of("date") {
+                    Some(date) => Some(parse_date(date, today)?),
+                    None => None,
+                };
+                let quantum = sub_matches
+                    .value_of("quantum")
+                    .map(|_| value_t!(sub_matches, "quantum", f64).unwrap_or_else(|e| e.exit()));
+                let patch = HabitLogPatch::new(
+                    quantum,
+                    sub_matches.value_of("notes").map(String::from),
+                    date,
+                );
+                if patch.is_empty() {
+                    return Err(HabytError::validation(
+                        "Nothing to change, pass --quantum, --notes or --date",
+                    ));
+                }
+
+                store_log.update(
+                    log_id,
+                    patch,
+                    &store,
+                    today,
+                    sub_matches.is_present("force"),
+                )?;
+                habitctl.save_log(&store_log)?;
+                if format != OutputFormat::Table {
+                    let log_entry = store_log.get(log_id).unwrap();
+                    return write_records(
+                        &[LogRecord::new(log_entry, store.name_of(log_entry.habit_id))],
+                        format,
+                        io::stdout(),
+                    );
+                }
+                println!("Before: {}", describe_log(&store, &log_entry));
+                println!(
+                    "After:  {}",
+                    describe_log(&store, store_log.get(log_id).unwrap())
+                );
+            }
+            ("rm", Some(sub_matches)) => {
+                let log_id: u32 = value_t!(sub_matches, "log-id", u32).unwrap_or_else(|e| e.exit());
+                let mut store_log = habitctl.load_log()?;
+                let log_entry = store_log.delete(log_id).ok_or_else(|| {
+                    HabytError::NotFound(format!("There's no log entry with id {}", log_id))
+                })?;
+                habitctl.save_log(&store_log)?;
+                if format != OutputFormat::Table {
+                    return write_records(
+                        &[LogRecord::new(
+                            &log_entry,
+                            store.name_of(log_entry.habit_id),
+                        )],
+                        format,
+                        io::stdout(),
+                    );
+                }
+                println!("Removed: {}", describe_log(&store, &log_entry));
+            }
+            _ => {
+                let sub_matches = log_matches;
+                let id: u32 = value_t!(sub_matches, "id", u32).unwrap_or_else(|e| e.exit());
+
+                let today = Local::today().naive_local();
+                let date = match sub_matches.value_of("date") {
+                    Some(date) => parse_date(date, today)?,
+                    None => today,
+                };
+                let mut store_log = habitctl.load_log()?;
+                let log_id = store_log.add(
+                    HabitLog::try_new_dated(
+                        id,
+                        value_t!(sub_matches, "quantum", f64).unwrap_or(0.),
+                        Some(String::from(sub_matches.value_of("notes").unwrap_or(""))),
+                        date,
+                    )?,
+                    &store,
+                    today,
+                    sub_matches.is_present("force"),
+                )?;
+                habitctl.save_log(&store_log)?;
+                if format != OutputFormat::Table {
+                    return write_records(
+                        &[LogRecord::new(
+                            store_log.get(log_id).unwrap(),
+                            store.name_of(id),
+                        )],
+                        format,
+                        io::stdout(),
+                    );
+                }
+            }
+        },
         ("llist", Some(_)) => {
-            habitctl.load_log();
-            let logs = habitctl.store_log.get_log_by_habit();
-            // let quantum: Vec<f64> = vec![];
+            let store_log = habitctl.load_log()?;
+            if format != OutputFormat::Table {
+                let mut records: Vec<LogRecord> = store_log
+                    .list()
+                    .into_iter()
+                    .map(|log_entry| LogRecord::new(log_entry, store.name_of(log_entry.habit_id)))
+                    .collect();
+                records.sort_by_key(|record| record.id);
+                return write_records(&records, format, io::stdout());
+            }
+            let logs = store_log.get_log_by_habit();
+            let today = Local::today().naive_local();
 
             // println!("You've made {} entries so far...", logs.len());
             for (id, log_entries) in logs.iter() {
-                println!("-> {} for a day \n", id);
-                for (log_entry_date, log_entry_quantum) in log_entries.iter() {
-                    println!("-> {} for a {} \n", log_entry_date, log_entry_quantum);
+                let habit = match store.get(*id) {
+                    Some(habit) => habit,
+                    None => {
+                        println!("-> {} for a day \n", id);
+                        for log_entry in log_entries.iter() {
+                            println!(
+                                "-> #{} {} for a {} \n",
+                                log_entry.id, log_entry.date, log_entry.quantum
+                            );
+                        }
+                        continue;
+                    }
+                };
+
+                println!("-> {} for {} \n", habit.name, describe_goal(habit));
+                let totals = quantum_by_period(habit, &store_log.list_by_habit(*id), today);
+                for log_entry in log_entries.iter() {
+                    let period = Period::containing(habit.streak, log_entry.date);
+                    let over_the_limit = habit.is_negative()
+                        && !habit.is_met(totals.get(&period).copied().unwrap_or(0.));
+                    println!(
+                        "-> #{} {} for a {}{} \n",
+                        log_entry.id,
+                        log_entry.date,
+                        log_entry.quantum,
+                        if over_the_limit {
+                            " (over the limit!)"
+                        } else {
+                            ""
+                        }
+                    );
+                }
+            }
+        }
+        ("restore", Some(sub_matches)) if sub_matches.is_present("id") => {
+            let id: u32 = value_t!(sub_matches, "id", u32).unwrap_or_else(|e| e.exit());
+            let mut store_log = habitctl.load_log()?;
+            let habit = store.restore(id, &mut store_log)?.clone();
+            habitctl.commit(&store, &store_log)?;
+            if format != OutputFormat::Table {
+                return write_records(
+                    &[HabitRecord::new(&habit, Local::today().naive_local())],
+                    format,
+                    io::stdout(),
+                );
+            }
+            println!(
+                "Habyt is tracking {} with id {} again, along with {} log entries!",
+                habit.name,
+                habit.id,
+                store_log.list_by_habit(id).len()
+            );
+        }
+        ("restore", Some(sub_matches)) => match sub_matches.value_of("backup") {
+            Some(backup) => {
+                habitctl.restore_backup(backup)?;
+                if format != OutputFormat::Table {
+                    return write_records(
+                        &[BackupRecord {
+                            backup: backup.to_string(),
+                        }],
+                        format,
+                        io::stdout(),
+                    );
+                }
+                println!("Your habits are back to how they were at {}!", backup);
+            }
+            None => {
+                let backups = habitctl.backups()?;
+                if format != OutputFormat::Table {
+                    let records: Vec<BackupRecord> = backups
+                        .iter()
+                        .map(|backup| BackupRecord {
+                            backup: backup.to_string(),
+                        })
+                        .collect();
+                    return write_records(&records, format, io::stdout());
+                }
+                println!("There are {} backups to roll back to...", backups.len());
+                for backup in backups.iter() {
+                    println!("-> {}", backup);
+                }
+            }
+        },
+        ("archive", Some(_)) => {
+            let store_log = habitctl.load_log()?;
+            let mut habits = store.list_archived();
+            habits.sort_by_key(|habit| habit.id);
+            let logs = store_log.list_archived();
+            if format != OutputFormat::Table {
+                let today = Local::today().naive_local();
+                let records: Vec<HabitRecord> = habits
+                    .iter()
+                    .map(|habit| HabitRecord::new(habit, today))
+                    .collect();
+                return write_records(&records, format, io::stdout());
+            }
+
+            println!("There are {} habits in the archive...", habits.len());
+            for habit in habits.iter() {
+                let entries = logs
+                    .iter()
+                    .filter(|log_entry| log_entry.habit_id == habit.id)
+                    .count();
+                println!(
+                    "-> {} ({}) for {}, {} log entries, archived on {} \n",
+                    habit.name,
+                    habit.id,
+                    describe_goal(habit),
+                    entries,
+                    habit
+                        .archived_at
+                        .map(|archived_at| archived_at.date().to_string())
+                        .unwrap_or_else(|| "an unknown day".into())
+                );
+            }
+        }
+        ("doctor", Some(sub_matches)) => {
+            let store_log = habitctl.load_log()?;
+            let orphans = orphaned_logs(&store, &store_log);
+            if format != OutputFormat::Table {
+                let records: Vec<LogRecord> = orphans
+                    .iter()
+                    .map(|log_entry| LogRecord::new(log_entry, store.name_of(log_entry.habit_id)))
+                    .collect();
+                if sub_matches.is_present("fix") {
+                    bury_orphans(&mut store, &store_log);
+                    habitctl.save(&store)?;
+                }
+                return write_records(&records, format, io::stdout());
+            }
+            if orphans.is_empty() {
+                println!("All log entries belong to a habit, nothing to fix!");
+                return Ok(());
+            }
+
+            println!(
+                "{} log entries belong to habits that are gone...",
+                orphans.len()
+            );
+            for log_entry in orphans.iter() {
+                println!("-> {}", describe_log(&store, log_entry));
+            }
+            if sub_matches.is_present("fix") {
+                let buried = bury_orphans(&mut store, &store_log);
+                habitctl.save(&store)?;
+                println!(
+                    "Kept {} log entries under a tombstone of their habit!",
+                    buried
+                );
+            } else {
+                println!("Run habyt doctor --fix to keep them under a tombstone of their habit.");
+            }
+        }
+        ("export", Some(sub_matches)) => {
+            let store_log = habitctl.load_log()?;
+            let mut records = vec![];
+            if let Some(dir) = sub_matches.value_of_os("csv").map(Path::new) {
+                let (habits, logs) =
+                    export_csv(&store, &store_log, dir, Local::today().naive_local())?;
+                records.push(ExportRecord {
+                    path: dir.display().to_string(),
+                    habits,
+                    logs,
+                });
+                if format == OutputFormat::Table {
+                    println!(
+                        "Exported {} habits and {} log entries to {}!",
+                        habits,
+                        logs,
+                        dir.display()
+                    );
+                }
+            }
+            if let Some(file) = sub_matches.value_of_os("ics").map(Path::new) {
+                let (habits, days) = export_ics(&store, &store_log, file, Utc::now().naive_utc())?;
+                records.push(ExportRecord {
+                    path: file.display().to_string(),
+                    habits,
+                    logs: days,
+                });
+                if format == OutputFormat::Table {
+                    println!(
+                        "Exported {} habits and {} completed days to {}!",
+                        habits,
+                        days,
+                        file.display()
+                    );
                 }
             }
+            if format != OutputFormat::Table {
+                return write_records(&records, format, io::stdout());
+            }
+        }
+        ("import", Some(sub_matches)) => {
+            let mut store_log = habitctl.load_log()?;
+            let today = Local::today().naive_local();
+            let dry_run = sub_matches.is_present("dry-run");
+            let mut records = vec![];
+            for file in sub_matches
+                .values_of_os("csv")
+                .into_iter()
+                .flatten()
+                .map(Path::new)
+            {
+                let import = import_csv(&mut store, &mut store_log, File::open(file)?, today)?;
+                records.extend(ImportRecord::from_csv(&file.display().to_string(), &import));
+                if format != OutputFormat::Table {
+                    continue;
+                }
+
+                println!(
+                    "{} {} habits and {} log entries from {}...",
+                    if dry_run { "Would import" } else { "Imported" },
+                    import.habits,
+                    import.logs,
+                    file.display()
+                );
+                for duplicate in import.duplicates.iter() {
+                    println!("-> line {} skipped: {}", duplicate.line, duplicate.reason);
+                }
+                for rejected in import.rejected.iter() {
+                    println!("-> line {} rejected: {}", rejected.line, rejected.reason);
+                }
+                for unmapped in import.unmapped.iter() {
+                    println!("-> line {}: {}", unmapped.line, unmapped.reason);
+                }
+            }
+            if let Some(dir) = sub_matches.value_of_os("loop").map(Path::new) {
+                let import = import_loop(&mut store, &mut store_log, dir, today)?;
+                records.extend(ImportRecord::from_loop(&dir.display().to_string(), &import));
+                if format == OutputFormat::Table {
+                    println!(
+                        "{} {} habits and {} log entries from Loop Habit Tracker...",
+                        if dry_run { "Would import" } else { "Imported" },
+                        import.habits,
+                        import.logs
+                    );
+                    for unmapped in import.unmapped.iter() {
+                        println!("-> {}: {}", unmapped.habit, unmapped.note);
+                    }
+                    for rejected in import.rejected.iter() {
+                        println!("-> {} rejected: {}", rejected.habit, rejected.note);
+                    }
+                }
+            }
+            if !dry_run {
+                habitctl.commit(&store, &store_log)?;
+            }
+            if format != OutputFormat::Table {
+                return write_records(&records, format, io::stdout());
+            }
+        }
+        ("import-yaml", Some(_)) => {
+            let (habits, logs) = HabitStoreSqlite::open_in(&data_dir)?
+                .import_yaml(&mut HabitStoreFs::open(&data_dir)?)?;
+            if format != OutputFormat::Table {
+                return write_records(
+                    &[ImportRecord::imported(
+                        &data_dir.display().to_string(),
+                        habits,
+                        logs,
+                    )],
+                    format,
+                    io::stdout(),
+                );
+            }
+            println!(
+                "Imported {} habits and {} log entries into the SQLite store!",
+                habits, logs
+            );
         }
         _ => {
             // no subcommand used
         }
     }
+    Ok(())
+}
+
+/// A log entry in words, e.g. "#12 writing: 750 words on 2021-06-01 (rainy)"
+fn describe_log(store: &HabitStore, log_entry: &HabitLog) -> String {
+    let notes = if log_entry.notes.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", log_entry.notes)
+    };
+    match store.get(log_entry.habit_id) {
+        Some(habit) => format!(
+            "#{} {}: {} {} on {}{}",
+            log_entry.id, habit.name, log_entry.quantum, habit.unit, log_entry.date, notes
+        ),
+        None => {
+            let name = match store
+                .tombstone(log_entry.habit_id)
+                .and_then(|tombstone| tombstone.name.as_ref())
+            {
+                Some(name) => format!("{} (deleted)", name),
+                None => format!("habit {}", log_entry.habit_id),
+            };
+            format!(
+                "#{} {}: {} on {}{}",
+                log_entry.id, name, log_entry.quantum, log_entry.date, notes
+            )
+        }
+    }
+}
+
+/// How far along a habit is in words, e.g. "400 of 750 words, 350 to go"
+fn describe_progress(habit: &Habit, progress: &Progress) -> String {
+    let amount = if habit.is_negative() {
+        let left = if progress.remaining < 0. {
+            format!("{} over", -progress.remaining)
+        } else {
+            format!("{} left", progress.remaining)
+        };
+        format!(
+            "{} of at most {} {}, {}",
+            progress.logged, habit.quantum, habit.unit, left
+        )
+    } else {
+        format!(
+            "{} of {} {}, {} to go",
+            progress.logged, habit.quantum, habit.unit, progress.remaining
+        )
+    };
+    match progress.times {
+        Some((days_met, needed)) => format!(
+            "{} today, {} of {} times this {}",
+            amount,
+            days_met,
+            needed,
+            habit.streak.noun()
+        ),
+        None => amount,
+    }
+}
+
+/// Weeks shown by `habyt heatmap`, enough for a year
+const HEATMAP_WEEKS: u32 = 53;
+
+/// Draw a heatmap with a column per week and a row per day of the week, under the months
+fn render_heatmap(weeks: &[HeatmapWeek], colors: bool) -> String {
+    let cell = |shade: Shade| {
+        let (ascii, color) = match shade {
+            Shade::Untracked => return "  ".to_string(),
+            Shade::Nothing => ('.', 238),
+            Shade::Low => (':', 22),
+            Shade::Medium => ('+', 28),
+            Shade::High => ('*', 34),
+            Shade::Full => ('#', 40),
+            Shade::Over => ('X', 160),
+        };
+        if colors {
+            format!("\x1b[38;5;{}m\u{25a0}\x1b[0m ", color)
+        } else {
+            format!("{} ", ascii)
+        }
+    };
+
+    let mut months = vec![' '; 4 + 2 * weeks.len()];
+    let mut last_month = None;
+    let mut free_from = 0;
+    for (column, week) in weeks.iter().enumerate() {
+        let month = week.week.end.month();
+        if last_month != Some(month) {
+            // Skip a month's name that would run into the previous one or off the edge
+            let label = week.week.end.format("%b").to_string();
+            let at = 4 + 2 * column;
+            if at >= free_from && at + label.len() <= months.len() {
+                months.splice(at..at + label.len(), label.chars());
+                free_from = at + label.len() + 1;
+            }
+            last_month = Some(month);
+        }
+    }
+    let mut rendered = months
+        .into_iter()
+        .collect::<String>()
+        .trim_end()
+        .to_string();
+    rendered.push('\n');
+
+    for (weekday, label) in ["Mon", "", "Wed", "", "Fri", "", "Sun"].iter().enumerate() {
+        let mut row = format!("{:<4}", label);
+        for week in weeks {
+            row.push_str(&cell(week.days[weekday]));
+        }
+        rendered.push_str(row.trim_end());
+        rendered.push('\n');
+    }
+
+    rendered.push_str("\n    less ");
+    for shade in [
+        Shade::Nothing,
+        Shade::Low,
+        Shade::Medium,
+        Shade::High,
+        Shade::Full,
+    ] {
+        rendered.push_str(&cell(shade));
+    }
+    rendered.push_str(&format!("more, {}over the limit\n", cell(Shade::Over)));
+    rendered
+}
+
+/// The goal of a habit in words, e.g. "750 words a day", "at most 5 unit a day" or
+/// "3 km on Mon, Wed, Fri"
+fn describe_goal(habit: &Habit) -> String {
+    let amount = if habit.is_negative() {
+        format!("at most {} {}", habit.quantum, habit.unit)
+    } else {
+        format!("{} {}", habit.quantum, habit.unit)
+    };
+    match &habit.schedule {
+        None => format!("{} a {}", amount, habit.streak.noun()),
+        Some(Schedule::Weekdays(weekdays)) => format!(
+            "{} on {}",
+            amount,
+            weekdays
+                .iter()
+                .map(|day| day.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Some(Schedule::TimesPer(times)) => {
+            format!("{} {} times a {}", amount, times, habit.streak.noun())
+        }
+        Some(Schedule::EveryNDays { days, .. }) => format!("{} every {} days", amount, days),
+    }
 }
@@ -0,0 +1,42 @@
+use crate::error::{HabytError, HabytResult};
+use crate::lock::StoreLock;
+use crate::store::{HabitLogStore, HabitStore};
+use std::time::Duration;
+
+/// A place `habyt` loads its habits and logs from, and saves them back to.
+///
+/// `HabitStoreFs` keeps them as YAML files in the data directory, while `HabitStoreMem`
+/// keeps them in memory, which is handy for tests and for embedding `lib`.
+pub trait StorageBackend {
+    /// Keep other processes from changing the store until the returned lock is dropped.
+    ///
+    /// Take it before `load` and hold it past `save`, so concurrent `habyt` runs don't
+    /// overwrite each other's changes.
+    fn lock(&self, _timeout: Duration) -> HabytResult<StoreLock> {
+        Ok(StoreLock::unlocked())
+    }
+
+    fn load(&mut self) -> HabytResult<HabitStore>;
+
+    fn save(&mut self, store: &HabitStore) -> HabytResult<()>;
+
+    fn load_log(&mut self) -> HabytResult<HabitLogStore>;
+
+    fn save_log(&mut self, store_log: &HabitLogStore) -> HabytResult<()>;
+
+    /// Persist the habits and their logs together, so neither is saved without the other
+    fn commit(&mut self, store: &HabitStore, store_log: &HabitLogStore) -> HabytResult<()>;
+
+    /// Names of the backups that can be restored, newest first
+    fn backups(&self) -> HabytResult<Vec<String>> {
+        Ok(vec![])
+    }
+
+    /// Roll the habits and logs back to how they were in the given backup
+    fn restore_backup(&mut self, name: &str) -> HabytResult<()> {
+        Err(HabytError::NotFound(format!(
+            "There's no backup named {}",
+            name
+        )))
+    }
+}
@@ -0,0 +1,476 @@
+use crate::error::{HabytError, HabytResult};
+use crate::models::{parse_schedule, HabitDraft, HabitLog, HabitName, Kind, Streak};
+use crate::output::{write_records, HabitRecord, LogRecord, OutputFormat};
+use crate::store::{HabitLogStore, HabitStore};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+/// Habits are exported to this file of the export directory
+pub const HABITS_CSV: &str = "habits.csv";
+/// Log entries are exported to this file of the export directory
+pub const LOGS_CSV: &str = "logs.csv";
+
+/// A row of a habits CSV file, with the columns `HabitRecord` is written with.
+///
+/// Only `name` and `quantum` are needed; `schedule` wins over `streak` when both are there.
+/// Habits always come in active, so `active` and `paused` are only read to report that.
+#[derive(Deserialize)]
+struct HabitRow {
+    name: String,
+    quantum: f64,
+    #[serde(default)]
+    unit: String,
+    #[serde(default)]
+    streak: Option<Streak>,
+    #[serde(default)]
+    kind: Option<Kind>,
+    #[serde(default)]
+    schedule: Option<String>,
+    #[serde(default)]
+    active: Option<bool>,
+    #[serde(default)]
+    paused: Option<bool>,
+    #[serde(default)]
+    created_at: Option<NaiveDateTime>,
+}
+
+/// A row of a logs CSV file, with the columns `LogRecord` is written with.
+///
+/// Entries find their habit by `habit_name`, as ids differ from one store to the next.
+#[derive(Deserialize)]
+struct LogRow {
+    habit_name: String,
+    quantum: f64,
+    #[serde(default)]
+    notes: Option<String>,
+    date: NaiveDate,
+}
+
+/// A row of a CSV file that was not imported, and why
+#[derive(PartialEq, Debug, Clone)]
+pub struct CsvRowIssue {
+    /// The line of the file the row is on, the header being line 1
+    pub line: u64,
+    pub reason: String,
+}
+
+/// What importing a CSV file did
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct CsvImport {
+    pub habits: usize,
+    pub logs: usize,
+    /// Habits named like one already there, and log entries for a habit and date already logged
+    pub duplicates: Vec<CsvRowIssue>,
+    /// Rows that could not be read or did not pass validation
+    pub rejected: Vec<CsvRowIssue>,
+    /// Rows imported with a column left out, such as a habit that was paused
+    pub unmapped: Vec<CsvRowIssue>,
+}
+
+/// Write the habits and log entries to `HABITS_CSV` and `LOGS_CSV` in `dir`, creating it if need be.
+///
+/// Returns the number of habits and log entries exported.
+pub fn export_csv(
+    store: &HabitStore,
+    store_log: &HabitLogStore,
+    dir: &Path,
+    today: NaiveDate,
+) -> HabytResult<(usize, usize)> {
+    fs::create_dir_all(dir)?;
+
+    let mut habits: Vec<HabitRecord> = store
+        .list()
+        .into_iter()
+        .map(|habit| HabitRecord::new(habit, today))
+        .collect();
+    habits.sort_by_key(|record| record.id);
+    write_records(
+        &habits,
+        OutputFormat::Csv,
+        File::create(dir.join(HABITS_CSV))?,
+    )?;
+
+    let mut logs: Vec<LogRecord> = store_log
+        .list()
+        .into_iter()
+        .map(|log_entry| LogRecord::new(log_entry, store.name_of(log_entry.habit_id)))
+        .collect();
+    logs.sort_by_key(|record| record.id);
+    write_records(&logs, OutputFormat::Csv, File::create(dir.join(LOGS_CSV))?)?;
+
+    Ok((habits.len(), logs.len()))
+}
+
+/// Import habits or log entries from a CSV file written by `export_csv`, or by hand.
+///
+/// A file with a `date` column holds log entries, otherwise one with a `name` column holds
+/// habits, and an empty file holds nothing. Rows that are duplicates or fail validation are
+/// reported and skipped; the rest go into `store` and `store_log`, which are left for the
+/// caller to save.
+pub fn import_csv<R: Read>(
+    store: &mut HabitStore,
+    store_log: &mut HabitLogStore,
+    reader: R,
+    today: NaiveDate,
+) -> HabytResult<CsvImport> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let headers = reader.headers()?.clone();
+    let mut import = CsvImport::default();
+
+    if headers.is_empty() {
+        // What `export_csv` writes when there's nothing to export
+        return Ok(import);
+    } else if headers.iter().any(|header| header == "date") {
+        // Only what was logged before the import counts, a file may well log a day twice
+        let logged: HashSet<(HabitName, NaiveDate)> = store_log
+            .list()
+            .into_iter()
+            .filter_map(|log_entry| {
+                store
+                    .get(log_entry.habit_id)
+                    .map(|habit| (habit.name.clone(), log_entry.date))
+            })
+            .collect();
+        for record in reader.records() {
+            let record = record?;
+            let line = record.position().map_or(0, |position| position.line());
+            let issue = |reason: String| CsvRowIssue { line, reason };
+
+            let row: LogRow = match record.deserialize(Some(&headers)) {
+                Ok(row) => row,
+                Err(e) => {
+                    import.rejected.push(issue(e.to_string()));
+                    continue;
+                }
+            };
+            let habit_id = match store
+                .list()
+                .into_iter()
+                .find(|habit| habit.name.0 == row.habit_name)
+            {
+                Some(habit) => habit.id,
+                None => {
+                    import
+                        .rejected
+                        .push(issue(format!("There's no habit named {}", row.habit_name)));
+                    continue;
+                }
+            };
+            let key = (HabitName(row.habit_name), row.date);
+            if logged.contains(&key) {
+                import
+                    .duplicates
+                    .push(issue(format!("{} is already logged on {}", key.0, key.1)));
+                continue;
+            }
+            let added = HabitLog::try_new_dated(habit_id, row.quantum, row.notes, row.date)
+                .and_then(|log_entry| store_log.add(log_entry, store, today, false));
+            match added {
+                Ok(_) => import.logs += 1,
+                Err(e) => import.rejected.push(issue(e.to_string())),
+            }
+        }
+    } else if headers.iter().any(|header| header == "name") {
+        for record in reader.records() {
+            let record = record?;
+            let line = record.position().map_or(0, |position| position.line());
+            let issue = |reason: String| CsvRowIssue { line, reason };
+
+            let row: HabitRow = match record.deserialize(Some(&headers)) {
+                Ok(row) => row,
+                Err(e) => {
+                    import.rejected.push(issue(e.to_string()));
+                    continue;
+                }
+            };
+            if store.list().iter().any(|habit| habit.name.0 == row.name) {
+                import
+                    .duplicates
+                    .push(issue(format!("There's already a habit named {}", row.name)));
+                continue;
+            }
+            let set_aside = if row.active == Some(false) {
+                Some("inactive")
+            } else if row.paused == Some(true) {
+                Some("paused")
+            } else {
+                None
+            };
+            let name = row.name.clone();
+            match create_habit(store, row, today) {
+                Ok(()) => import.habits += 1,
+                Err(e) => {
+                    import.rejected.push(issue(e.to_string()));
+                    continue;
+                }
+            }
+            if let Some(state) = set_aside {
+                import.unmapped.push(issue(format!(
+                    "{} was {}, it's imported active; pause it to set it aside again",
+                    name, state
+                )));
+            }
+        }
+    } else {
+        return Err(HabytError::validation(
+            "The CSV file has neither a name column for habits nor a date column for logs",
+        ));
+    }
+    Ok(import)
+}
+
+fn create_habit(store: &mut HabitStore, row: HabitRow, today: NaiveDate) -> HabytResult<()> {
+    let mut draft = HabitDraft::try_from(
+        row.name,
+        row.quantum,
+        row.unit,
+        row.streak,
+        None,
+        row.kind,
+        None,
+    )?;
+    if let Some(schedule) = row.schedule.filter(|schedule| !schedule.is_empty()) {
+        // Intervals count from when the habit was created, as far as that's known
+        let from = row.created_at.map_or(today, |created_at| created_at.date());
+        let (streak, schedule) = parse_schedule(&schedule, from)?;
+        draft = draft.with_schedule(streak, schedule);
+    }
+    let id = store.create(draft)?;
+    if let Some(created_at) = row.created_at {
+        store.get_mut(id)?.created_at = Some(created_at);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod csv_io_tests {
+    use crate::csv_io::{export_csv, import_csv, HABITS_CSV, LOGS_CSV};
+    use crate::models::{DeletePolicy, HabitDraft, HabitLog};
+    use crate::store::{HabitLogStore, HabitStore};
+    use crate::test_support::day;
+    use std::fs::File;
+    use std::path::Path;
+
+    fn writing_logged_twice() -> (HabitStore, HabitLogStore) {
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+        let id = store
+            .create(HabitDraft::new("writing".into(), 750., "words".into()))
+            .expect("Failed to create a habit");
+        store.get_mut(id).unwrap().created_at = Some(day("2021-06-01").and_hms(7, 0, 0));
+        for date in ["2021-06-01", "2021-06-02"] {
+            store_log
+                .add(
+                    HabitLog::new_dated(id, 800., None, day(date)),
+                    &store,
+                    day("2021-06-02"),
+                    false,
+                )
+                .expect("Failed to log the habit");
+        }
+        (store, store_log)
+    }
+
+    fn import_file(
+        store: &mut HabitStore,
+        store_log: &mut HabitLogStore,
+        file: &Path,
+    ) -> crate::csv_io::CsvImport {
+        import_csv(
+            store,
+            store_log,
+            File::open(file).expect("Failed to open the export"),
+            day("2021-06-02"),
+        )
+        .expect("Failed to import the file")
+    }
+
+    #[test]
+    fn exported_csv_imports_into_another_store() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let (store, store_log) = writing_logged_twice();
+        export_csv(&store, &store_log, dir.path(), day("2021-06-02"))
+            .expect("Failed to export the store");
+        let mut other = HabitStore::new();
+        let mut other_log = HabitLogStore::new();
+
+        // act
+        let habits = import_file(&mut other, &mut other_log, &dir.path().join(HABITS_CSV));
+        let logs = import_file(&mut other, &mut other_log, &dir.path().join(LOGS_CSV));
+
+        // assert
+        assert_eq!((habits.habits, logs.logs), (1, 2));
+        assert_eq!(other.list()[0].name.0, "writing");
+        assert_eq!(other_log.list().len(), 2);
+    }
+
+    #[test]
+    fn a_day_logged_twice_imports_twice() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let (store, mut store_log) = writing_logged_twice();
+        let id = store.list()[0].id;
+        store_log
+            .add(
+                HabitLog::new_dated(id, 200., Some("evening".into()), day("2021-06-02")),
+                &store,
+                day("2021-06-02"),
+                false,
+            )
+            .expect("Failed to log the habit");
+        export_csv(&store, &store_log, dir.path(), day("2021-06-02"))
+            .expect("Failed to export the store");
+        let mut other = HabitStore::new();
+        let mut other_log = HabitLogStore::new();
+
+        // act
+        import_file(&mut other, &mut other_log, &dir.path().join(HABITS_CSV));
+        let logs = import_file(&mut other, &mut other_log, &dir.path().join(LOGS_CSV));
+
+        // assert
+        assert_eq!(logs.logs, 3);
+        assert!(logs.duplicates.is_empty());
+        assert_eq!(
+            other_log
+                .list()
+                .iter()
+                .filter(|log_entry| log_entry.date == day("2021-06-02"))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn logs_of_deleted_habits_keep_their_habit_name() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let (mut store, mut store_log) = writing_logged_twice();
+        let id = store.list()[0].id;
+        store.delete_with(id, DeletePolicy::Tombstone, &mut store_log);
+
+        // act
+        export_csv(&store, &store_log, dir.path(), day("2021-06-02"))
+            .expect("Failed to export the store");
+
+        // assert
+        let logs = std::fs::read_to_string(dir.path().join(LOGS_CSV)).unwrap();
+        assert_eq!(logs.lines().count(), 3);
+        assert!(logs.lines().skip(1).all(|line| line.contains(",writing,")));
+    }
+
+    #[test]
+    fn an_exported_empty_store_imports_as_nothing() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        export_csv(
+            &HabitStore::new(),
+            &HabitLogStore::new(),
+            dir.path(),
+            day("2021-06-02"),
+        )
+        .expect("Failed to export the store");
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+
+        // act
+        let habits = import_file(&mut store, &mut store_log, &dir.path().join(HABITS_CSV));
+        let logs = import_file(&mut store, &mut store_log, &dir.path().join(LOGS_CSV));
+
+        // assert
+        assert_eq!(habits, Default::default());
+        assert_eq!(logs, Default::default());
+    }
+
+    #[test]
+    fn logs_already_there_are_skipped_as_duplicates() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let (mut store, mut store_log) = writing_logged_twice();
+        export_csv(&store, &store_log, dir.path(), day("2021-06-02"))
+            .expect("Failed to export the store");
+
+        // act
+        let again = import_file(&mut store, &mut store_log, &dir.path().join(LOGS_CSV));
+
+        // assert
+        assert_eq!(again.logs, 0);
+        assert_eq!(again.duplicates.len(), 2);
+        assert_eq!(again.duplicates[0].line, 2);
+        assert_eq!(store_log.list().len(), 2);
+    }
+
+    #[test]
+    fn invalid_rows_are_rejected_with_their_line() {
+        // arrange
+        let habits = "name,quantum,unit\nrunning,5,km\nfar too many words here,1,\n";
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+
+        // act
+        let import = import_csv(
+            &mut store,
+            &mut store_log,
+            habits.as_bytes(),
+            day("2021-06-02"),
+        )
+        .expect("Failed to import the habits");
+
+        // assert
+        assert_eq!(import.habits, 1);
+        assert_eq!(import.rejected.len(), 1);
+        assert_eq!(import.rejected[0].line, 3);
+    }
+
+    #[test]
+    fn logs_dated_in_the_future_are_rejected() {
+        // arrange
+        let (mut store, _) = writing_logged_twice();
+        let mut store_log = HabitLogStore::new();
+        let logs = "habit_name,quantum,date\nwriting,900,2021-06-02\nwriting,900,2021-06-03\n";
+
+        // act
+        let import = import_csv(
+            &mut store,
+            &mut store_log,
+            logs.as_bytes(),
+            day("2021-06-02"),
+        )
+        .expect("Failed to import the logs");
+
+        // assert
+        assert_eq!(import.logs, 1);
+        assert_eq!(import.rejected.len(), 1);
+        assert_eq!(import.rejected[0].line, 3);
+        assert!(import.rejected[0].reason.contains("in the future"));
+    }
+
+    #[test]
+    fn habits_set_aside_come_in_active_and_are_reported() {
+        // arrange
+        let habits = "name,quantum,active,paused\nrunning,5,false,false\nreading,10,true,true\n\
+                      writing,750,true,false\n";
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+
+        // act
+        let import = import_csv(
+            &mut store,
+            &mut store_log,
+            habits.as_bytes(),
+            day("2021-06-02"),
+        )
+        .expect("Failed to import the habits");
+
+        // assert
+        assert_eq!(import.habits, 3);
+        assert!(store.list().iter().all(|habit| habit.active));
+        let lines: Vec<u64> = import.unmapped.iter().map(|issue| issue.line).collect();
+        assert_eq!(lines, vec![2, 3]);
+        assert!(import.unmapped[0].reason.contains("inactive"));
+    }
+}
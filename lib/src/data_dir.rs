@@ -0,0 +1,162 @@
+extern crate dirs;
+use crate::error::{HabytError, HabytResult};
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Environment variable pointing at the directory to keep habits in
+pub const HABYT_HOME: &str = "HABYT_HOME";
+
+const HABYT_DIR: &str = ".habyt";
+const XDG_HABYT_DIR: &str = "habyt";
+const PROFILES_DIR: &str = "profiles";
+
+/// The directory holding the store files, see `create_data_dir` to make it on first use.
+///
+/// The first of these wins:
+/// 1. `explicit`, e.g. from `--data-dir`
+/// 2. the `HABYT_HOME` environment variable
+/// 3. `~/.habyt`, when it's already there from an earlier `habyt`
+/// 4. the XDG data directory, e.g. `~/.local/share/habyt`
+/// 5. `~/.habyt`
+///
+/// Each named `profile` gets a directory of its own under `profiles/`.
+pub fn data_dir(explicit: Option<&Path>, profile: Option<&str>) -> HabytResult<PathBuf> {
+    let base = base_dir(
+        explicit,
+        env::var_os(HABYT_HOME).map(PathBuf::from),
+        dirs::home_dir(),
+        dirs::data_dir(),
+    )?;
+    match profile {
+        Some(profile) => profile_dir(&base, profile),
+        None => Ok(base),
+    }
+}
+
+/// Create `store_dir` unless it's already there; true when it had to, i.e. on the first run
+pub fn create_data_dir(store_dir: &Path) -> HabytResult<bool> {
+    if store_dir.is_dir() {
+        return Ok(false);
+    }
+    fs::create_dir_all(store_dir)?;
+    Ok(true)
+}
+
+fn base_dir(
+    explicit: Option<&Path>,
+    habyt_home: Option<PathBuf>,
+    home: Option<PathBuf>,
+    xdg_data: Option<PathBuf>,
+) -> HabytResult<PathBuf> {
+    if let Some(dir) = explicit {
+        return Ok(dir.to_path_buf());
+    }
+    if let Some(dir) = habyt_home.filter(|dir| !dir.as_os_str().is_empty()) {
+        return Ok(dir);
+    }
+
+    let legacy = home.map(|home| home.join(HABYT_DIR));
+    match (legacy, xdg_data) {
+        (Some(legacy), _) if legacy.is_dir() => Ok(legacy),
+        (_, Some(xdg_data)) => Ok(xdg_data.join(XDG_HABYT_DIR)),
+        (Some(legacy), None) => Ok(legacy),
+        (None, None) => Err(HabytError::NotFound(format!(
+            "Could not find a directory to keep habits in, set {} or pass --data-dir",
+            HABYT_HOME
+        ))),
+    }
+}
+
+/// Profile names end up in paths, so they're kept to letters, digits, `-` and `_`
+fn profile_dir(base: &Path, profile: &str) -> HabytResult<PathBuf> {
+    let valid = !profile.is_empty()
+        && profile
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(HabytError::validation(&format!(
+            "'{}' is not a profile name, use letters, digits, - and _",
+            profile
+        )));
+    }
+    Ok(base.join(PROFILES_DIR).join(profile))
+}
+
+#[cfg(test)]
+mod data_dir_tests {
+    use crate::data_dir::{base_dir, create_data_dir, profile_dir};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn explicit_and_env_dirs_come_first() {
+        let home = Some(PathBuf::from("/home/someone"));
+        let xdg = Some(PathBuf::from("/home/someone/.local/share"));
+        let habyt_home = Some(PathBuf::from("/srv/habyt"));
+
+        assert_eq!(
+            base_dir(
+                Some(Path::new("/tmp/h")),
+                habyt_home.clone(),
+                home.clone(),
+                xdg.clone()
+            )
+            .unwrap(),
+            PathBuf::from("/tmp/h")
+        );
+        assert_eq!(
+            base_dir(None, habyt_home, home, xdg).unwrap(),
+            PathBuf::from("/srv/habyt")
+        );
+    }
+
+    #[test]
+    fn existing_habyt_dirs_are_kept_over_xdg() {
+        // arrange
+        let home = tempfile::tempdir().unwrap();
+        let xdg = Some(PathBuf::from("/nowhere/.local/share"));
+
+        // act
+        let fresh = base_dir(None, None, Some(home.path().into()), xdg.clone()).unwrap();
+        std::fs::create_dir(home.path().join(".habyt")).unwrap();
+        let existing = base_dir(None, None, Some(home.path().into()), xdg).unwrap();
+
+        // assert
+        assert_eq!(fresh, PathBuf::from("/nowhere/.local/share/habyt"));
+        assert_eq!(existing, home.path().join(".habyt"));
+    }
+
+    #[test]
+    fn no_home_at_all_should_fail() {
+        assert!(base_dir(None, Some(PathBuf::new()), None, None).is_err());
+    }
+
+    #[test]
+    fn profiles_get_their_own_dir() {
+        let base = Path::new("/srv/habyt");
+
+        assert_eq!(
+            profile_dir(base, "work").unwrap(),
+            PathBuf::from("/srv/habyt/profiles/work")
+        );
+        assert!(profile_dir(base, "../work").is_err());
+        assert!(profile_dir(base, "").is_err());
+    }
+
+    #[test]
+    fn only_the_first_run_creates_the_data_dir() {
+        // arrange
+        let home = tempfile::tempdir().unwrap();
+        let store_dir = home.path().join("profiles").join("work");
+
+        // act
+        let first = create_data_dir(&store_dir).unwrap();
+        let second = create_data_dir(&store_dir).unwrap();
+
+        // assert
+        assert!(first);
+        assert!(!second);
+        assert!(store_dir.is_dir());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::error::{HabytError, HabytResult};
+use chrono::{Duration, NaiveDate};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Read a date the way people type it on the command line, relative to `today`.
+///
+/// Accepts ISO dates (`2021-06-01`), `today`, `yesterday`, `tomorrow`, and offsets in
+/// days or weeks such as `-3d`, `+1d` or `-2w`.
+pub fn parse_date(input: &str, today: NaiveDate) -> HabytResult<NaiveDate> {
+    let input = input.trim().to_lowercase();
+    match input.as_str() {
+        "today" => return Ok(today),
+        "yesterday" => return Ok(today.pred()),
+        "tomorrow" => return Ok(today.succ()),
+        _ => {}
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(&input, DATE_FORMAT) {
+        return Ok(date);
+    }
+
+    let invalid = || {
+        HabytError::validation(&format!(
+            "'{}' is not a date, try 2021-06-01, yesterday or -3d",
+            input
+        ))
+    };
+    if !input.starts_with('-') && !input.starts_with('+') {
+        return Err(invalid());
+    }
+    let (amount, days_per_unit) = if let Some(amount) = input.strip_suffix('d') {
+        (amount, 1)
+    } else if let Some(amount) = input.strip_suffix('w') {
+        (amount, 7)
+    } else {
+        return Err(invalid());
+    };
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+    today
+        .checked_add_signed(Duration::days(amount * days_per_unit))
+        .ok_or_else(invalid)
+}
+
+/// Make sure a log entry's date is one the habit could have been done on.
+///
+/// Dates after `today`, or before `tracked_since` when it's known, are refused unless `allow`ed.
+pub fn validate_log_date(
+    date: NaiveDate,
+    today: NaiveDate,
+    tracked_since: Option<NaiveDate>,
+    allow: bool,
+) -> HabytResult<()> {
+    if allow {
+        return Ok(());
+    }
+    if date > today {
+        return Err(HabytError::validation(&format!(
+            "{} is in the future, you can only log what's already done",
+            date
+        )));
+    }
+    match tracked_since {
+        Some(since) if date < since => Err(HabytError::validation(&format!(
+            "{} is before the habit was tracked, which started on {}",
+            date, since
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Make sure a range of days given on the command line isn't back to front
+pub fn validate_range(from: NaiveDate, to: NaiveDate) -> HabytResult<()> {
+    if from > to {
+        return Err(HabytError::validation(&format!(
+            "{} is after {}, there's nothing in between",
+            from, to
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod dates_tests {
+    use crate::dates::{parse_date, validate_log_date, validate_range};
+    use crate::test_support::day;
+
+    #[test]
+    fn iso_and_relative_dates_are_understood() {
+        let today = day("2021-06-10");
+
+        assert_eq!(parse_date("2021-06-01", today).unwrap(), day("2021-06-01"));
+        assert_eq!(parse_date("yesterday", today).unwrap(), day("2021-06-09"));
+        assert_eq!(parse_date("Today", today).unwrap(), today);
+        assert_eq!(parse_date("-3d", today).unwrap(), day("2021-06-07"));
+        assert_eq!(parse_date("+1d", today).unwrap(), day("2021-06-11"));
+        assert_eq!(parse_date("-2w", today).unwrap(), day("2021-05-27"));
+    }
+
+    #[test]
+    fn parsing_gibberish_should_fail() {
+        let today = day("2021-06-10");
+
+        assert!(parse_date("last tuesday", today).is_err());
+        assert!(parse_date("-3x", today).is_err());
+        assert!(parse_date("3d", today).is_err());
+    }
+
+    #[test]
+    fn logging_in_the_future_or_before_tracking_should_fail_unless_allowed() {
+        let today = day("2021-06-10");
+        let since = Some(day("2021-06-01"));
+
+        assert!(validate_log_date(day("2021-06-05"), today, since, false).is_ok());
+        assert!(validate_log_date(day("2021-06-11"), today, since, false).is_err());
+        assert!(validate_log_date(day("2021-05-31"), today, since, false).is_err());
+        assert!(validate_log_date(day("2021-06-11"), today, since, true).is_ok());
+    }
+
+    #[test]
+    fn ranges_ending_before_they_start_should_fail() {
+        assert!(validate_range(day("2021-06-01"), day("2021-06-10")).is_ok());
+        assert!(validate_range(day("2021-06-10"), day("2021-06-10")).is_ok());
+        assert!(validate_range(day("2021-06-11"), day("2021-06-10")).is_err());
+    }
+}
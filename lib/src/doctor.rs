@@ -0,0 +1,73 @@
+use crate::models::HabitLog;
+use crate::store::{HabitLogStore, HabitStore};
+
+use chrono::Local;
+use std::collections::BTreeSet;
+
+/// Log entries whose habit is gone without a trace, neither archived nor tombstoned.
+///
+/// These are left behind by habits deleted before `DeletePolicy` existed.
+pub fn orphaned_logs<'a>(store: &HabitStore, store_log: &'a HabitLogStore) -> Vec<&'a HabitLog> {
+    let mut orphans: Vec<&HabitLog> = store_log
+        .list()
+        .into_iter()
+        .filter(|log_entry| {
+            let habit_id = log_entry.habit_id;
+            store.get(habit_id).is_none()
+                && store.archived(habit_id).is_none()
+                && store.tombstone(habit_id).is_none()
+        })
+        .collect();
+    orphans.sort_by_key(|log_entry| log_entry.id);
+    orphans
+}
+
+/// Leave a nameless tombstone for every habit orphaned logs point at, so they're kept.
+///
+/// Returns the number of log entries that were orphaned.
+pub fn bury_orphans(store: &mut HabitStore, store_log: &HabitLogStore) -> usize {
+    let orphans = orphaned_logs(store, store_log);
+    let habit_ids: BTreeSet<_> = orphans.iter().map(|log_entry| log_entry.habit_id).collect();
+    let now = Local::now().naive_local();
+    for habit_id in habit_ids {
+        store.bury(habit_id, None, now);
+    }
+    orphans.len()
+}
+
+#[cfg(test)]
+mod doctor_tests {
+    use crate::doctor::{bury_orphans, orphaned_logs};
+    use crate::models::{HabitDraft, HabitLog};
+    use crate::store::{HabitLogStore, HabitStore};
+    use chrono::Local;
+    use fake::Fake;
+
+    #[test]
+    fn logs_of_plainly_deleted_habits_are_found_and_buried() {
+        // arrange
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+        let draft = HabitDraft::new((3..25).fake::<String>(), 700.0, (3..15).fake::<String>());
+        let habit_id = store.create(draft).expect("Failed to create habit.");
+        store_log
+            .add(
+                HabitLog::new(habit_id, 350.0, None),
+                &store,
+                Local::today().naive_local(),
+                false,
+            )
+            .expect("Failed to log habit.");
+        store.delete(habit_id);
+
+        // act
+        let found = orphaned_logs(&store, &store_log).len();
+        let buried = bury_orphans(&mut store, &store_log);
+
+        // assert
+        assert_eq!(found, 1);
+        assert_eq!(buried, 1);
+        assert!(orphaned_logs(&store, &store_log).is_empty());
+        assert_eq!(store.tombstone(habit_id).unwrap().name, None);
+    }
+}
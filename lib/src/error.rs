@@ -0,0 +1,150 @@
+use crate::models::ValidationError;
+use std::error::Error;
+use std::fmt;
+
+/// Everything that can go wrong while tracking habits
+#[derive(Debug)]
+pub enum HabytError {
+    /// Reading or writing the store failed
+    Io(std::io::Error),
+    /// The SQLite store refused a query
+    Database(rusqlite::Error),
+    /// The store holds data that could not be (de)serialized
+    Parse(String),
+    /// Records could not be read from or written to a CSV or JSON file
+    Format(String),
+    /// The input did not pass validation
+    Validation(ValidationError),
+    /// There's nothing with the given id
+    NotFound(String),
+    /// The change clashes with what's already there
+    Conflict(String),
+}
+
+pub type HabytResult<T> = Result<T, HabytError>;
+
+impl HabytError {
+    pub fn validation(msg: &str) -> HabytError {
+        HabytError::Validation(ValidationError::new(msg))
+    }
+}
+
+impl fmt::Display for HabytError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HabytError::Io(e) => write!(f, "Failed to access the habit store: {}", e),
+            HabytError::Database(e) => write!(f, "Failed to query the habit database: {}", e),
+            HabytError::Parse(details) => write!(f, "Failed to parse the habit store: {}", details),
+            HabytError::Format(details) => write!(f, "Failed to read or write the {}", details),
+            HabytError::Validation(e) => write!(f, "{}", e),
+            HabytError::NotFound(details) => write!(f, "{}", details),
+            HabytError::Conflict(details) => write!(f, "{}", details),
+        }
+    }
+}
+
+impl Error for HabytError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HabytError::Io(e) => Some(e),
+            HabytError::Database(e) => Some(e),
+            HabytError::Validation(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for HabytError {
+    fn from(e: std::io::Error) -> Self {
+        HabytError::Io(e)
+    }
+}
+
+impl From<serde_yaml::Error> for HabytError {
+    fn from(e: serde_yaml::Error) -> Self {
+        HabytError::Parse(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for HabytError {
+    fn from(e: serde_json::Error) -> Self {
+        if e.is_io() {
+            return HabytError::Io(e.into());
+        }
+        HabytError::Format(format!("JSON: {}", e))
+    }
+}
+
+impl From<csv::Error> for HabytError {
+    fn from(e: csv::Error) -> Self {
+        let details = e.to_string();
+        match e.into_kind() {
+            csv::ErrorKind::Io(e) => HabytError::Io(e),
+            _ => HabytError::Format(format!("CSV: {}", details)),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for HabytError {
+    fn from(e: rusqlite::Error) -> Self {
+        HabytError::Database(e)
+    }
+}
+
+impl From<ValidationError> for HabytError {
+    fn from(e: ValidationError) -> Self {
+        HabytError::Validation(e)
+    }
+}
+
+#[cfg(test)]
+mod error_tests {
+    use crate::error::HabytError;
+    use std::io;
+
+    /// A writer that always fails, like a full disk
+    struct Broken;
+
+    impl io::Write for Broken {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn csv_and_json_failures_are_told_apart_from_the_store() {
+        // arrange
+        let mut reader = csv::Reader::from_reader("a,b\n1,2,3\n".as_bytes());
+        let csv_error = reader.records().next().unwrap().unwrap_err();
+        let json_error = serde_json::from_str::<u32>("one").unwrap_err();
+
+        // act
+        let csv_error = HabytError::from(csv_error).to_string();
+        let json_error = HabytError::from(json_error).to_string();
+
+        // assert
+        assert!(csv_error.starts_with("Failed to read or write the CSV"));
+        assert!(json_error.starts_with("Failed to read or write the JSON"));
+    }
+
+    #[test]
+    fn csv_and_json_io_failures_are_io_errors() {
+        // arrange
+        let mut writer = csv::Writer::from_writer(Broken);
+        writer.write_record(["a", "b"]).unwrap();
+        let csv_error = writer.flush().map_err(csv::Error::from).unwrap_err();
+        let json_error = serde_json::to_writer(Broken, &[1, 2]).unwrap_err();
+
+        // act
+        let csv_error = HabytError::from(csv_error);
+        let json_error = HabytError::from(json_error);
+
+        // assert
+        assert!(matches!(csv_error, HabytError::Io(_)));
+        assert!(matches!(json_error, HabytError::Io(_)));
+    }
+}
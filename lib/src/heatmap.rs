@@ -0,0 +1,133 @@
+use crate::models::{Habit, HabitLog, HasPosNeg, Streak};
+use crate::streak::{quantum_by_day, Period};
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+
+/// How much of its quantum a habit reached on a day
+#[derive(Serialize, PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Shade {
+    /// Not tracked that day: before tracking began, while paused, or yet to come
+    Untracked,
+    /// Nothing logged
+    Nothing,
+    /// Less than a third of the quantum
+    Low,
+    /// Less than two thirds of the quantum
+    Medium,
+    /// Less than the whole quantum
+    High,
+    /// The quantum was reached, or a negative habit stayed within its limit
+    Full,
+    /// A negative habit went over its limit
+    Over,
+}
+
+/// One column of the heatmap, Monday first
+#[derive(PartialEq, Debug, Clone)]
+pub struct HeatmapWeek {
+    pub week: Period,
+    pub days: [Shade; 7],
+}
+
+/// How `habit` went on each day of the `weeks` ISO weeks up to the one `today` is in, oldest first
+pub fn heatmap(
+    habit: &Habit,
+    logs: &[&HabitLog],
+    today: NaiveDate,
+    weeks: u32,
+) -> Vec<HeatmapWeek> {
+    let by_day = quantum_by_day(habit, logs, today);
+    let tracked_since = habit
+        .tracked_since()
+        .into_iter()
+        .chain(by_day.keys().next().copied())
+        .min();
+
+    let current = Period::containing(Streak::Weekly, today);
+    let mut week = Period::containing(
+        Streak::Weekly,
+        current.start - Duration::weeks(weeks.saturating_sub(1) as i64),
+    );
+    let mut heatmap = vec![];
+    while week <= current {
+        let mut days = [Shade::Untracked; 7];
+        for (shade, day) in days.iter_mut().zip(week.days()) {
+            let tracked = tracked_since.is_some_and(|since| since <= day)
+                && day <= today
+                && !habit.is_paused_on(day);
+            if tracked {
+                *shade = shade_of(habit, by_day.get(&day).copied());
+            }
+        }
+        heatmap.push(HeatmapWeek { week, days });
+        week = week.next(Streak::Weekly);
+    }
+    heatmap
+}
+
+fn shade_of(habit: &Habit, quantum: Option<f64>) -> Shade {
+    let quantum = match quantum {
+        Some(quantum) => quantum,
+        None if habit.is_negative() => return Shade::Full,
+        None => return Shade::Nothing,
+    };
+    if habit.is_negative() {
+        return if habit.is_met(quantum) {
+            Shade::Full
+        } else {
+            Shade::Over
+        };
+    }
+    if habit.is_met(quantum) {
+        return Shade::Full;
+    }
+    let reached = quantum / habit.quantum;
+    if reached <= 0. {
+        Shade::Nothing
+    } else if reached < 1. / 3. {
+        Shade::Low
+    } else if reached < 2. / 3. {
+        Shade::Medium
+    } else {
+        Shade::High
+    }
+}
+
+#[cfg(test)]
+mod heatmap_tests {
+    use crate::heatmap::{heatmap, Shade};
+    use crate::models::{HabitLog, Kind, Streak};
+    use crate::test_support::{day, habit, log};
+
+    #[test]
+    fn days_are_shaded_by_the_share_of_quantum_reached() {
+        // arrange
+        let habit = habit(Streak::Daily, 900., Kind::Positive);
+        // Tuesday to Friday of one week
+        let logs = [
+            log("2021-06-08", 100.),
+            log("2021-06-09", 400.),
+            log("2021-06-11", 1000.),
+        ];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let heatmap = heatmap(&habit, &logs, day("2021-06-12"), 2);
+
+        // assert
+        assert_eq!(heatmap.len(), 2);
+        assert_eq!(heatmap[0].days, [Shade::Untracked; 7]);
+        assert_eq!(
+            heatmap[1].days,
+            [
+                Shade::Untracked,
+                Shade::Low,
+                Shade::Medium,
+                Shade::Nothing,
+                Shade::Full,
+                Shade::Nothing,
+                Shade::Untracked,
+            ]
+        );
+    }
+}
@@ -0,0 +1,527 @@
+use crate::error::HabytResult;
+use crate::models::{Habit, HasPosNeg, Schedule, Streak};
+use crate::output::schedule_spec;
+use crate::store::{HabitLogStore, HabitStore};
+use crate::streak::{quantum_by_day, Period};
+use chrono::naive::MAX_DATE;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Lines of an iCalendar file are folded once they get this long, in octets
+const ICS_LINE_LIMIT: usize = 75;
+
+/// Write the habits and log entries to `path` as an iCalendar file, see `to_ics`.
+///
+/// Returns the number of habits and of completed days exported.
+pub fn export_ics(
+    store: &HabitStore,
+    store_log: &HabitLogStore,
+    path: &Path,
+    stamp: NaiveDateTime,
+) -> HabytResult<(usize, usize)> {
+    let (ics, habits, completions) = calendar(store, store_log, stamp);
+    fs::write(path, ics)?;
+    Ok((habits, completions))
+}
+
+/// The habits and log entries as an iCalendar file, `stamp` being now in UTC.
+///
+/// Each active habit is an all-day event recurring as its `Streak` and schedule say, from
+/// the day tracking began, and starting over after each pause. Each day a habit was completed,
+/// see `completed_days`, is an all-day event of its own, with what was logged that day.
+pub fn to_ics(store: &HabitStore, store_log: &HabitLogStore, stamp: NaiveDateTime) -> String {
+    calendar(store, store_log, stamp).0
+}
+
+fn calendar(
+    store: &HabitStore,
+    store_log: &HabitLogStore,
+    stamp: NaiveDateTime,
+) -> (String, usize, usize) {
+    let stamp = stamp.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut habits = store.list();
+    habits.sort_by_key(|habit| habit.id);
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".into(),
+        "PRODID:-//habyt//habyt//EN".into(),
+        "CALSCALE:GREGORIAN".into(),
+    ];
+    let (mut recurring, mut completions) = (0, 0);
+    for habit in habits {
+        let logs = store_log.list_by_habit(habit.id);
+        let by_day = quantum_by_day(habit, &logs, MAX_DATE);
+
+        if habit.active {
+            let start = match &habit.schedule {
+                Some(Schedule::EveryNDays { from, .. }) => Some(*from),
+                _ => habit.tracked_since(),
+            }
+            .into_iter()
+            .chain(by_day.keys().next().copied())
+            .min();
+            let stretches = start.map_or(vec![], |start| unpaused_stretches(habit, start));
+            let mut first = true;
+            for stretch in stretches {
+                let Some(dtstart) = first_occurrence(habit, stretch.start)
+                    .filter(|dtstart| stretch.end.is_none_or(|end| *dtstart <= end))
+                else {
+                    continue;
+                };
+                // Completed days point at the first event, later ones follow a pause
+                let uid = if first {
+                    format!("habit-{}@habyt", habit.id)
+                } else {
+                    format!("habit-{}-from-{}@habyt", habit.id, ics_date(dtstart))
+                };
+                let until = stretch
+                    .end
+                    .map_or(String::new(), |end| format!(";UNTIL={}", ics_date(end)));
+                lines.extend(vec![
+                    "BEGIN:VEVENT".to_string(),
+                    format!("UID:{}", uid),
+                    format!("DTSTAMP:{}", stamp),
+                    format!("DTSTART;VALUE=DATE:{}", ics_date(dtstart)),
+                    format!("RRULE:{}{}", rrule(habit, dtstart), until),
+                    format!("SUMMARY:{}", escape(&habit.name.0)),
+                    format!("DESCRIPTION:{}", escape(&goal(habit))),
+                    "TRANSP:TRANSPARENT".into(),
+                    "END:VEVENT".into(),
+                ]);
+                first = false;
+            }
+            if !first {
+                recurring += 1;
+            }
+        }
+
+        for (date, quantum) in completed_days(habit, &by_day) {
+            lines.extend(vec![
+                "BEGIN:VEVENT".to_string(),
+                format!("UID:habit-{}-{}@habyt", habit.id, ics_date(date)),
+                format!("DTSTAMP:{}", stamp),
+                format!("DTSTART;VALUE=DATE:{}", ics_date(date)),
+                format!("DTEND;VALUE=DATE:{}", ics_date(date.succ())),
+                format!(
+                    "SUMMARY:{}",
+                    escape(format!("{}: {} {}", habit.name, quantum, habit.unit).trim_end())
+                ),
+                format!("RELATED-TO:habit-{}@habyt", habit.id),
+                "STATUS:CONFIRMED".into(),
+                "TRANSP:TRANSPARENT".into(),
+                "END:VEVENT".into(),
+            ]);
+            completions += 1;
+        }
+    }
+    lines.push("END:VCALENDAR".into());
+
+    let ics = lines
+        .iter()
+        .map(|line| fold(line))
+        .collect::<Vec<_>>()
+        .join("\r\n")
+        + "\r\n";
+    (ics, recurring, completions)
+}
+
+/// The days `habit` was completed on, with what was logged each day.
+///
+/// Habits done daily or a number of times per period complete the days their quantum is met,
+/// see `Habit::is_met`. Habits with a quantum per week or month complete the day the period's
+/// total meets it, and negative ones every day they were logged with the total still within
+/// the limit.
+fn completed_days(habit: &Habit, by_day: &BTreeMap<NaiveDate, f64>) -> Vec<(NaiveDate, f64)> {
+    let per_day =
+        habit.streak == Streak::Daily || matches!(habit.schedule, Some(Schedule::TimesPer(_)));
+    let mut period_total: Option<(Period, f64)> = None;
+    let mut completed = vec![];
+    for (date, quantum) in by_day.iter().map(|(date, quantum)| (*date, *quantum)) {
+        let met = if per_day {
+            habit.is_met(quantum)
+        } else {
+            let period = Period::containing(habit.streak, date);
+            let before = match period_total {
+                Some((total_period, total)) if total_period == period => total,
+                _ => 0.,
+            };
+            period_total = Some((period, before + quantum));
+            habit.is_met(before + quantum) && (habit.is_negative() || !habit.is_met(before))
+        };
+        if met {
+            completed.push((date, quantum));
+        }
+    }
+    completed
+}
+
+/// A run of days from `start` the habit wasn't paused in, open-ended for the last one
+struct Stretch {
+    start: NaiveDate,
+    end: Option<NaiveDate>,
+}
+
+/// The days from `start` on, split around the pauses with an end, so each stretch recurs on
+/// its own instead of listing every paused day.
+///
+/// A habit paused until it's resumed is inactive, and not exported as recurring at all.
+fn unpaused_stretches(habit: &Habit, start: NaiveDate) -> Vec<Stretch> {
+    let mut stretches = vec![];
+    let mut from = start;
+    for pause in habit.pauses.iter() {
+        let Some(until) = pause.until.filter(|until| *until >= from) else {
+            continue;
+        };
+        if pause.from > from {
+            stretches.push(Stretch {
+                start: from,
+                end: Some(pause.from.pred()),
+            });
+        }
+        from = until.succ();
+    }
+    stretches.push(Stretch {
+        start: from,
+        end: None,
+    });
+    stretches
+}
+
+/// The first day from `start` on the habit recurs on, which the event has to start on.
+///
+/// None when the schedule never comes around, e.g. no weekdays at all.
+fn first_occurrence(habit: &Habit, start: NaiveDate) -> Option<NaiveDate> {
+    if monthly_on_the_last_day(habit, start) {
+        return Some(Period::containing(Streak::Monthly, start).end);
+    }
+    match &habit.schedule {
+        Some(Schedule::EveryNDays { days: 0, .. }) => None,
+        Some(Schedule::EveryNDays { from, days }) if *from < start => {
+            let behind = (start - *from).num_days() % *days as i64;
+            let ahead = (*days as i64 - behind) % *days as i64;
+            Some(start + Duration::days(ahead))
+        }
+        Some(Schedule::EveryNDays { from, .. }) => Some(*from),
+        // Any weekday on a schedule comes around within a week
+        _ => start.iter_days().take(7).find(|day| habit.is_due_on(*day)),
+    }
+}
+
+/// Monthly recurrences starting after the 28th would skip the months too short for that day,
+/// so they recur on the last day of each month instead
+fn monthly_on_the_last_day(habit: &Habit, start: NaiveDate) -> bool {
+    let monthly = matches!(habit.schedule, None | Some(Schedule::TimesPer(_)))
+        && habit.streak == Streak::Monthly;
+    monthly && start.day() > 28
+}
+
+/// How often a habit recurs, e.g. "FREQ=WEEKLY;BYDAY=MO,WE,FR".
+///
+/// Habits done a number of times per period recur once a period, as a rule can't leave the
+/// days open; the description says how many times.
+fn rrule(habit: &Habit, dtstart: NaiveDate) -> String {
+    match &habit.schedule {
+        Some(Schedule::Weekdays(weekdays)) => format!(
+            "FREQ=WEEKLY;BYDAY={}",
+            weekdays
+                .iter()
+                .map(|day| ics_weekday(*day))
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        Some(Schedule::EveryNDays { days, .. }) => format!("FREQ=DAILY;INTERVAL={}", days),
+        Some(Schedule::TimesPer(_)) | None => match habit.streak {
+            Streak::Daily => "FREQ=DAILY".into(),
+            Streak::Weekly => "FREQ=WEEKLY".into(),
+            Streak::Monthly if monthly_on_the_last_day(habit, dtstart) => {
+                "FREQ=MONTHLY;BYMONTHDAY=-1".into()
+            }
+            Streak::Monthly => "FREQ=MONTHLY".into(),
+        },
+    }
+}
+
+/// The goal of a habit, e.g. "750 words, mon,wed,fri"
+fn goal(habit: &Habit) -> String {
+    let amount = if habit.is_negative() {
+        format!("at most {} {}", habit.quantum, habit.unit)
+    } else {
+        format!("{} {}", habit.quantum, habit.unit)
+    };
+    format!("{}, {}", amount, schedule_spec(habit))
+}
+
+fn ics_date(date: NaiveDate) -> String {
+    format!("{:04}{:02}{:02}", date.year(), date.month(), date.day())
+}
+
+fn ics_weekday(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+/// Escape text the way iCalendar wants it
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Fold a line longer than `ICS_LINE_LIMIT` octets onto lines starting with a space
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        // Continuation lines lose one octet to the leading space
+        if length + c.len_utf8() > ICS_LINE_LIMIT {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod ics_tests {
+    use crate::ics::{escape, fold, to_ics};
+    use crate::models::{HabitDraft, Kind, Schedule, Streak};
+    use crate::store::{HabitLogStore, HabitStore};
+    use crate::test_support::{add_logs, day};
+    use chrono::Weekday;
+
+    fn ics(store: &HabitStore, store_log: &HabitLogStore) -> String {
+        to_ics(store, store_log, day("2021-06-30").and_hms(9, 30, 0))
+    }
+
+    /// The lines of each event, unfolded
+    fn events(ics: &str) -> Vec<Vec<String>> {
+        ics.replace("\r\n ", "")
+            .split("BEGIN:VEVENT\r\n")
+            .skip(1)
+            .map(|event| event.split("\r\n").map(String::from).collect())
+            .collect()
+    }
+
+    #[test]
+    fn the_calendar_is_wrapped_and_stamped() {
+        // act
+        let ics = ics(&HabitStore::new(), &HabitLogStore::new());
+
+        // assert
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(events(&ics).is_empty());
+    }
+
+    #[test]
+    fn habits_recur_on_their_schedule_from_the_first_log() {
+        // arrange
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+        let draft = HabitDraft::new("writing".into(), 750., "words".into()).with_schedule(
+            Streak::Daily,
+            Some(Schedule::Weekdays(vec![Weekday::Mon, Weekday::Fri])),
+        );
+        let id = store.create(draft).expect("Failed to create a habit");
+        add_logs(&store, &mut store_log, id, &[("2021-06-07", 800.)]);
+
+        // act
+        let events = events(&ics(&store, &store_log));
+
+        // assert
+        let recurring = &events[0];
+        assert!(recurring.contains(&"UID:habit-1@habyt".into()));
+        assert!(recurring.contains(&"DTSTAMP:20210630T093000Z".into()));
+        assert!(recurring.contains(&"DTSTART;VALUE=DATE:20210607".into()));
+        assert!(recurring.contains(&"RRULE:FREQ=WEEKLY;BYDAY=MO,FR".into()));
+        assert!(recurring.contains(&"DESCRIPTION:750 words\\, mon\\,fri".into()));
+    }
+
+    #[test]
+    fn only_days_the_quantum_was_met_are_completed() {
+        // arrange
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+        let id = store
+            .create(HabitDraft::new("running".into(), 5., "km".into()))
+            .expect("Failed to create a habit");
+        add_logs(
+            &store,
+            &mut store_log,
+            id,
+            &[("2021-06-07", 3.), ("2021-06-08", 3.), ("2021-06-08", 2.)],
+        );
+
+        // act
+        let events = events(&ics(&store, &store_log));
+
+        // assert
+        assert_eq!(events.len(), 2);
+        assert!(events[1].contains(&"DTSTART;VALUE=DATE:20210608".into()));
+        assert!(events[1].contains(&"SUMMARY:running: 5 km".into()));
+        assert!(events[1].contains(&"STATUS:CONFIRMED".into()));
+    }
+
+    #[test]
+    fn negative_habits_complete_the_days_within_their_limit() {
+        // arrange
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+        let draft = HabitDraft::from(
+            "coffee".into(),
+            2.,
+            "cups".into(),
+            None,
+            None,
+            Some(Kind::Negative),
+            None,
+        );
+        let id = store.create(draft).expect("Failed to create a habit");
+        add_logs(
+            &store,
+            &mut store_log,
+            id,
+            &[("2021-06-07", 1.), ("2021-06-08", 3.)],
+        );
+
+        // act
+        let events = events(&ics(&store, &store_log));
+
+        // assert
+        assert_eq!(events.len(), 2);
+        assert!(events[1].contains(&"SUMMARY:coffee: 1 cups".into()));
+    }
+
+    #[test]
+    fn weekly_habits_complete_the_day_their_goal_is_reached() {
+        // arrange
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+        let draft =
+            HabitDraft::new("running".into(), 10., "km".into()).with_schedule(Streak::Weekly, None);
+        let id = store.create(draft).expect("Failed to create a habit");
+        // Monday to Thursday of the same ISO week
+        add_logs(
+            &store,
+            &mut store_log,
+            id,
+            &[
+                ("2021-06-07", 4.),
+                ("2021-06-08", 4.),
+                ("2021-06-09", 4.),
+                ("2021-06-10", 4.),
+            ],
+        );
+
+        // act
+        let events = events(&ics(&store, &store_log));
+
+        // assert
+        assert_eq!(events.len(), 2);
+        assert!(events[1].contains(&"DTSTART;VALUE=DATE:20210609".into()));
+    }
+
+    #[test]
+    fn paused_days_are_left_out_of_the_recurrence() {
+        // arrange
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+        let id = store
+            .create(HabitDraft::new("running".into(), 5., "km".into()))
+            .expect("Failed to create a habit");
+        add_logs(&store, &mut store_log, id, &[("2021-06-07", 5.)]);
+        store
+            .pause(id, day("2021-06-10"), Some(day("2021-06-12")))
+            .expect("Failed to pause the habit");
+
+        // act
+        let events = events(&ics(&store, &store_log));
+
+        // assert
+        assert!(events[0].contains(&"DTSTART;VALUE=DATE:20210607".into()));
+        assert!(events[0].contains(&"RRULE:FREQ=DAILY;UNTIL=20210609".into()));
+        assert!(events[1].contains(&"UID:habit-1-from-20210613@habyt".into()));
+        assert!(events[1].contains(&"DTSTART;VALUE=DATE:20210613".into()));
+        assert!(events[1].contains(&"RRULE:FREQ=DAILY".into()));
+        assert!(events
+            .iter()
+            .all(|event| !event.iter().any(|line| line.starts_with("EXDATE"))));
+    }
+
+    #[test]
+    fn recurrences_start_on_the_first_scheduled_day() {
+        // arrange
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+        let draft = HabitDraft::new("writing".into(), 750., "words".into()).with_schedule(
+            Streak::Daily,
+            Some(Schedule::Weekdays(vec![Weekday::Mon, Weekday::Fri])),
+        );
+        let id = store.create(draft).expect("Failed to create a habit");
+        // A Wednesday, logged though it's not on the schedule
+        add_logs(&store, &mut store_log, id, &[("2021-06-09", 800.)]);
+
+        // act
+        let events = events(&ics(&store, &store_log));
+
+        // assert
+        assert!(events[0].contains(&"DTSTART;VALUE=DATE:20210611".into()));
+    }
+
+    #[test]
+    fn monthly_habits_started_late_in_a_month_recur_on_its_last_day() {
+        // arrange
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+        let draft = HabitDraft::new("budget".into(), 1., "review".into())
+            .with_schedule(Streak::Monthly, None);
+        let id = store.create(draft).expect("Failed to create a habit");
+        add_logs(&store, &mut store_log, id, &[("2021-01-30", 1.)]);
+
+        // act
+        let events = events(&ics(&store, &store_log));
+
+        // assert
+        assert!(events[0].contains(&"DTSTART;VALUE=DATE:20210131".into()));
+        assert!(events[0].contains(&"RRULE:FREQ=MONTHLY;BYMONTHDAY=-1".into()));
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(escape("a;b,c\\d\ne"), "a\\;b\\,c\\\\d\\ne");
+    }
+
+    #[test]
+    fn long_lines_are_folded_at_75_octets() {
+        // arrange
+        let line = format!("SUMMARY:{}", "é".repeat(60));
+
+        // act
+        let folded = fold(&line);
+
+        // assert
+        let lines: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.len() <= 75));
+        assert!(lines[1].starts_with(' '));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn short_lines_are_left_as_they_are() {
+        assert_eq!(fold("SUMMARY:writing"), "SUMMARY:writing");
+    }
+}
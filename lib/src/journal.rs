@@ -0,0 +1,236 @@
+use crate::models::{HabitId, HabitLog};
+use crate::store::{HabitLogStore, HabitStore};
+use chrono::NaiveDate;
+
+/// The log entries of a journal, oldest first: those from `from` to `to`, both included,
+/// and only of the habit `habit_id` if given
+pub fn journal_entries(
+    store_log: &HabitLogStore,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    habit_id: Option<HabitId>,
+) -> Vec<&HabitLog> {
+    let mut entries: Vec<&HabitLog> = store_log
+        .list()
+        .into_iter()
+        .filter(|log_entry| from.is_none_or(|from| log_entry.date >= from))
+        .filter(|log_entry| to.is_none_or(|to| log_entry.date <= to))
+        .filter(|log_entry| habit_id.is_none_or(|habit_id| log_entry.habit_id == habit_id))
+        .collect();
+    entries.sort_by_key(|log_entry| (log_entry.date, log_entry.id));
+    entries
+}
+
+/// Write `entries` as a Markdown journal with a section a day, e.g.
+///
+/// ```markdown
+/// ## 2021-06-07, Monday
+///
+/// - **writing**: 750 words
+///
+///   Rainy morning, wrote anyway.
+/// ```
+///
+/// `entries` are expected oldest first, as `journal_entries` gives them. Names and notes are
+/// escaped, so they show as they were typed rather than as Markdown.
+pub fn render_journal(store: &HabitStore, entries: &[&HabitLog]) -> String {
+    let mut journal = String::from("# Journal\n");
+    let mut day = None;
+    let mut after_notes = false;
+    for log_entry in entries {
+        if day != Some(log_entry.date) {
+            journal.push_str(&format!(
+                "\n## {}\n\n",
+                log_entry.date.format("%Y-%m-%d, %A")
+            ));
+            day = Some(log_entry.date);
+        } else if after_notes {
+            journal.push('\n');
+        }
+
+        let (name, unit) = match store.get(log_entry.habit_id) {
+            Some(habit) => (habit.name.0.clone(), habit.unit.0.clone()),
+            None => match store.name_of(log_entry.habit_id) {
+                Some(name) => (format!("{} (deleted)", name), String::new()),
+                None => (format!("habit {}", log_entry.habit_id), String::new()),
+            },
+        };
+        journal.push_str(
+            format!(
+                "- **{}**: {} {}",
+                escape_inline(&name),
+                log_entry.quantum,
+                escape_inline(&unit)
+            )
+            .trim_end(),
+        );
+        journal.push('\n');
+        if !log_entry.notes.trim().is_empty() {
+            // Indented under the item, so notes spanning lines stay with their entry
+            journal.push('\n');
+            for line in log_entry.notes.trim().lines() {
+                journal.push_str(format!("  {}", escape_line(line.trim())).trim_end());
+                journal.push('\n');
+            }
+        }
+        after_notes = !log_entry.notes.trim().is_empty();
+    }
+    journal
+}
+
+/// Escape what Markdown reads as emphasis, code, links, HTML or tables within a line
+fn escape_inline(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\`*_[]<>|~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escape a whole line, including what would start a heading, quote, list or rule
+fn escape_line(line: &str) -> String {
+    let escaped = escape_inline(line);
+    let digits = escaped.chars().take_while(char::is_ascii_digit).count();
+    match escaped.chars().next() {
+        Some('#' | '>' | '-' | '+' | '=') => format!("\\{}", escaped),
+        // An ordered list item, e.g. "1. Ran" or "2) Swam"
+        _ if digits > 0 && escaped[digits..].starts_with(['.', ')']) => {
+            format!("{}\\{}", &escaped[..digits], &escaped[digits..])
+        }
+        _ => escaped,
+    }
+}
+
+#[cfg(test)]
+mod journal_tests {
+    use crate::journal::{journal_entries, render_journal};
+    use crate::models::{DeletePolicy, HabitId, HabitLog};
+    use crate::store::{HabitLogStore, HabitStore};
+    use crate::test_support::{create, day};
+    use chrono::NaiveDate;
+
+    fn log(
+        store: &HabitStore,
+        store_log: &mut HabitLogStore,
+        entries: &[(HabitId, f64, &str, &str)],
+    ) {
+        for (habit_id, quantum, notes, date) in entries {
+            store_log
+                .add(
+                    HabitLog::new_dated(*habit_id, *quantum, Some(notes.to_string()), day(date)),
+                    store,
+                    day(date),
+                    true,
+                )
+                .expect("Failed to log the habit");
+        }
+    }
+
+    #[test]
+    fn the_journal_groups_entries_by_day() {
+        // arrange
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+        let writing = create(&mut store, "writing", 750., "words");
+        let running = create(&mut store, "running", 5., "km");
+        log(
+            &store,
+            &mut store_log,
+            &[
+                (running, 5., "", "2021-06-08"),
+                (writing, 800., "Rainy morning, wrote anyway.", "2021-06-07"),
+                (running, 4., "Windy", "2021-06-07"),
+            ],
+        );
+
+        // act
+        let entries = journal_entries(&store_log, None, None, None);
+        let journal = render_journal(&store, &entries);
+
+        // assert
+        assert_eq!(
+            journal,
+            "# Journal\n\
+             \n## 2021-06-07, Monday\n\n\
+             - **writing**: 800 words\n\n  Rainy morning, wrote anyway.\n\n\
+             - **running**: 4 km\n\n  Windy\n\
+             \n## 2021-06-08, Tuesday\n\n\
+             - **running**: 5 km\n"
+        );
+    }
+
+    #[test]
+    fn entries_are_picked_by_date_and_habit() {
+        // arrange
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+        let writing = create(&mut store, "writing", 750., "words");
+        let running = create(&mut store, "running", 5., "km");
+        log(
+            &store,
+            &mut store_log,
+            &[
+                (writing, 100., "", "2021-06-01"),
+                (running, 4., "", "2021-06-07"),
+                (writing, 800., "", "2021-06-07"),
+                (running, 5., "", "2021-06-08"),
+            ],
+        );
+
+        // act
+        let in_range = journal_entries(
+            &store_log,
+            Some(day("2021-06-07")),
+            Some(day("2021-06-07")),
+            None,
+        );
+        let of_running = journal_entries(&store_log, None, None, Some(running));
+
+        // assert
+        assert_eq!(in_range.len(), 2);
+        assert!(in_range.iter().all(|entry| entry.date == day("2021-06-07")));
+        let dates: Vec<NaiveDate> = of_running.iter().map(|entry| entry.date).collect();
+        assert_eq!(dates, vec![day("2021-06-07"), day("2021-06-08")]);
+    }
+
+    #[test]
+    fn a_deleted_habit_keeps_its_entries_under_its_tombstone() {
+        // arrange
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+        let swimming = create(&mut store, "swimming", 1., "km");
+        log(&store, &mut store_log, &[(swimming, 1., "", "2021-06-07")]);
+        store.delete_with(swimming, DeletePolicy::Tombstone, &mut store_log);
+
+        // act
+        let entries = journal_entries(&store_log, None, None, Some(swimming));
+        let journal = render_journal(&store, &entries);
+
+        // assert
+        assert_eq!(entries.len(), 1);
+        assert!(journal.contains("- **swimming (deleted)**: 1\n"));
+    }
+
+    #[test]
+    fn names_and_notes_are_escaped() {
+        // arrange
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+        let id = create(&mut store, "dry_january", 1., "days");
+        let notes = "# not a heading\n*not emphasis* or [a link](x)\n- not a list\n1. nor this";
+        log(&store, &mut store_log, &[(id, 1., notes, "2021-06-07")]);
+
+        // act
+        let journal = render_journal(&store, &journal_entries(&store_log, None, None, None));
+
+        // assert
+        assert!(journal.contains("- **dry\\_january**: 1 days\n"));
+        assert!(journal.contains("\n  \\# not a heading\n"));
+        assert!(journal.contains("\n  \\*not emphasis\\* or \\[a link\\](x)\n"));
+        assert!(journal.contains("\n  \\- not a list\n"));
+        assert!(journal.contains("\n  1\\. nor this\n"));
+    }
+}
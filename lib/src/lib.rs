@@ -4,10 +4,47 @@
 //! `lib` is a collection of re-usable `habyt` code
 //!
 
+mod backend;
+mod csv_io;
+mod data_dir;
+mod dates;
+mod doctor;
+mod error;
+mod heatmap;
+mod ics;
+mod journal;
+mod lock;
+mod loop_import;
+mod migrations;
 mod models;
+mod output;
+mod progress;
+mod stats;
 mod store;
 mod store_fs;
+mod store_mem;
+mod store_sqlite;
+mod streak;
+#[cfg(test)]
+mod test_support;
 
+pub use backend::*;
+pub use csv_io::*;
+pub use data_dir::*;
+pub use dates::*;
+pub use doctor::*;
+pub use error::*;
+pub use heatmap::*;
+pub use ics::*;
+pub use journal::*;
+pub use lock::*;
+pub use loop_import::*;
 pub use models::*;
+pub use output::*;
+pub use progress::*;
+pub use stats::*;
 pub use store::*;
 pub use store_fs::*;
+pub use store_mem::*;
+pub use store_sqlite::*;
+pub use streak::*;
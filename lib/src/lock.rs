@@ -0,0 +1,84 @@
+use crate::error::{HabytError, HabytResult};
+use fs2::FileExt;
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const LOCK_FILE: &str = ".lock";
+const RETRY_EVERY: Duration = Duration::from_millis(50);
+
+/// How long to wait for another `habyt` to let go of the store
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An exclusive hold on a store, for the whole load–modify–save cycle.
+///
+/// Other processes asking for the lock wait until this one is dropped.
+pub struct StoreLock {
+    file: Option<File>,
+}
+
+impl StoreLock {
+    /// A lock for stores nobody else can reach, like the in-memory one
+    pub fn unlocked() -> StoreLock {
+        StoreLock { file: None }
+    }
+
+    /// Lock `dir` through a `.lock` file within it, giving up after `timeout`
+    pub fn acquire(dir: &Path, timeout: Duration) -> HabytResult<StoreLock> {
+        let lock_file = dir.join(LOCK_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_file)?;
+
+        let started = Instant::now();
+        loop {
+            match file.try_lock_exclusive() {
+                Ok(()) => return Ok(StoreLock { file: Some(file) }),
+                Err(e) if e.kind() == fs2::lock_contended_error().kind() => {
+                    if started.elapsed() >= timeout {
+                        return Err(HabytError::Conflict(format!(
+                            "Another habyt is using {}, gave up waiting after {}s",
+                            dir.display(),
+                            timeout.as_secs_f32()
+                        )));
+                    }
+                    thread::sleep(RETRY_EVERY);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        if let Some(file) = self.file.take() {
+            let _ = FileExt::unlock(&file);
+        }
+    }
+}
+
+#[cfg(test)]
+mod store_lock_tests {
+    use crate::lock::StoreLock;
+    use std::time::Duration;
+
+    #[test]
+    fn a_held_lock_keeps_others_out_until_dropped() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let held = StoreLock::acquire(dir.path(), Duration::from_millis(100)).unwrap();
+
+        // act
+        let contended = StoreLock::acquire(dir.path(), Duration::from_millis(100));
+        drop(held);
+        let released = StoreLock::acquire(dir.path(), Duration::from_millis(100));
+
+        // assert
+        assert!(contended.is_err());
+        assert!(released.is_ok());
+    }
+}
@@ -0,0 +1,628 @@
+use crate::error::{HabytError, HabytResult};
+use crate::models::{DeletePolicy, HabitDraft, HabitId, HabitLog, Kind, Schedule, Streak};
+use crate::store::{HabitLogStore, HabitStore};
+use chrono::NaiveDate;
+use csv::StringRecord;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+/// The habits of a Loop Habit Tracker export, alongside a directory of checkmarks per habit
+pub const LOOP_HABITS_CSV: &str = "Habits.csv";
+const LOOP_CHECKMARKS_CSV: &str = "Checkmarks.csv";
+const LOOP_SCORES_CSV: &str = "Scores.csv";
+
+/// Loop keeps the values of numerical habits in thousandths
+const LOOP_NUMERICAL_SCALE: f64 = 1000.;
+
+/// What a checkmark of a yes/no habit says about a day, as Loop writes it
+const LOOP_YES_AUTO: i64 = 1;
+const LOOP_YES_MANUAL: i64 = 2;
+const LOOP_SKIP: i64 = 3;
+
+/// Something about a habit that was not imported as it was, and why
+#[derive(PartialEq, Debug, Clone)]
+pub struct LoopImportNote {
+    pub habit: String,
+    pub note: String,
+}
+
+/// What importing a Loop Habit Tracker export did
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct LoopImport {
+    pub habits: usize,
+    pub logs: usize,
+    /// Fields and checkmarks with no equivalent in habyt, left out or imported as something close
+    pub unmapped: Vec<LoopImportNote>,
+    /// Habits and checkmarks that could not be imported at all
+    pub rejected: Vec<LoopImportNote>,
+}
+
+impl LoopImport {
+    fn unmapped(&mut self, habit: &str, note: String) {
+        self.unmapped.push(LoopImportNote {
+            habit: habit.into(),
+            note,
+        });
+    }
+
+    fn rejected(&mut self, habit: &str, note: String) {
+        self.rejected.push(LoopImportNote {
+            habit: habit.into(),
+            note,
+        });
+    }
+}
+
+/// A row of Loop's Habits.csv, from either the older or the newer layout
+struct LoopHabit {
+    name: String,
+    question: String,
+    description: String,
+    color: String,
+    numerical: bool,
+    at_most: bool,
+    unit: String,
+    target: f64,
+    frequency: (u32, u32),
+    archived: bool,
+}
+
+impl LoopHabit {
+    fn from_record(headers: &StringRecord, record: &StringRecord) -> HabytResult<Self> {
+        let field = |name: &str| {
+            headers
+                .iter()
+                .position(|header| header == name)
+                .and_then(|index| record.get(index))
+                .map(str::trim)
+        };
+        let number = |name: &str| -> HabytResult<Option<f64>> {
+            match field(name).filter(|value| !value.is_empty()) {
+                Some(value) => value.parse().map(Some).map_err(|_| {
+                    HabytError::validation(&format!("'{}' is not a number for {}", value, name))
+                }),
+                None => Ok(None),
+            }
+        };
+        // Newer exports say how often as a fraction, older ones as repetitions in an interval
+        let numerator = number("FrequencyNumerator")?.or(number("NumRepetitions")?);
+        let denominator = number("FrequencyDenominator")?.or(number("Interval")?);
+        Ok(LoopHabit {
+            name: field("Name").unwrap_or_default().into(),
+            question: field("Question").unwrap_or_default().into(),
+            description: field("Description").unwrap_or_default().into(),
+            color: field("Color").unwrap_or_default().into(),
+            numerical: field("Type") == Some("NUMERICAL"),
+            at_most: field("Target Type") == Some("AT_MOST"),
+            unit: field("Unit").unwrap_or_default().into(),
+            target: number("Target Value")?.unwrap_or(1.),
+            frequency: (
+                numerator.unwrap_or(1.) as u32,
+                denominator.unwrap_or(1.) as u32,
+            ),
+            archived: field("Archived?") == Some("true"),
+        })
+    }
+}
+
+/// The `Streak` and schedule closest to doing a habit `times` in `days`, and whether it's exact
+fn loop_frequency(times: u32, days: u32, from: NaiveDate) -> (Streak, Option<Schedule>, bool) {
+    match (times, days) {
+        (times, days) if times == 0 || days == 0 => (Streak::Daily, None, false),
+        // Doing it more than once a day is as close to daily as habyt gets
+        (times, days) if times >= days => (Streak::Daily, None, times == days),
+        (1, 7) => (Streak::Weekly, None, true),
+        (times, 7) => (Streak::Weekly, Some(Schedule::TimesPer(times)), true),
+        (1, 30) | (1, 31) => (Streak::Monthly, None, true),
+        (times, 30) | (times, 31) => (Streak::Monthly, Some(Schedule::TimesPer(times)), true),
+        (1, days) => (
+            Streak::Daily,
+            Some(Schedule::EveryNDays { days, from }),
+            true,
+        ),
+        (times, days) if days < 7 => (
+            Streak::Weekly,
+            Some(Schedule::TimesPer((times.saturating_mul(7) / days).min(7))),
+            false,
+        ),
+        (times, days) => (
+            Streak::Monthly,
+            Some(Schedule::TimesPer((times.saturating_mul(30) / days).max(1))),
+            false,
+        ),
+    }
+}
+
+/// Import a Loop Habit Tracker export, unzipped into `dir`.
+///
+/// Habits come from `Habits.csv`, their logs from the `Checkmarks.csv` in each habit's own
+/// directory, or from the one next to `Habits.csv` that has a column per habit. A habit named
+/// like one already there gets the checkmarks for days it isn't logged on. What has no
+/// equivalent, such as scores, questions, colors and skipped days, is reported as unmapped.
+/// Nothing is saved; that's left to the caller.
+pub fn import_loop(
+    store: &mut HabitStore,
+    store_log: &mut HabitLogStore,
+    dir: &Path,
+    today: NaiveDate,
+) -> HabytResult<LoopImport> {
+    let habits_file = dir.join(LOOP_HABITS_CSV);
+    if !habits_file.exists() {
+        return Err(HabytError::NotFound(format!(
+            "There's no {} in {}, is it a Loop Habit Tracker export?",
+            LOOP_HABITS_CSV,
+            dir.display()
+        )));
+    }
+    let mut reader = csv::Reader::from_reader(File::open(habits_file)?);
+    let headers = reader.headers()?.clone();
+    let mut import = LoopImport::default();
+
+    let mut habits = vec![];
+    for record in reader.records() {
+        let habit = LoopHabit::from_record(&headers, &record?)?;
+        if !habit.name.is_empty() {
+            habits.push(habit);
+        }
+    }
+    let shared_checkmarks = read_shared_checkmarks(&dir.join(LOOP_CHECKMARKS_CSV))?;
+
+    for habit in habits.iter() {
+        let checkmarks = match habit_dir(dir, habit) {
+            Some(habit_dir) => {
+                if habit_dir.join(LOOP_SCORES_CSV).exists() {
+                    import.unmapped(&habit.name, "scores are worked out by habyt".into());
+                }
+                read_checkmarks(&habit_dir.join(LOOP_CHECKMARKS_CSV))?
+            }
+            None => shared_checkmarks
+                .get(&habit.name)
+                .cloned()
+                .unwrap_or_default(),
+        };
+        import_habit(store, store_log, habit, &checkmarks, today, &mut import);
+    }
+    Ok(import)
+}
+
+fn import_habit(
+    store: &mut HabitStore,
+    store_log: &mut HabitLogStore,
+    habit: &LoopHabit,
+    checkmarks: &[(NaiveDate, i64)],
+    today: NaiveDate,
+    import: &mut LoopImport,
+) {
+    let existing = store
+        .list()
+        .into_iter()
+        .find(|existing| existing.name.0 == habit.name)
+        .map(|existing| existing.id);
+    let id: HabitId = match existing {
+        Some(id) => id,
+        None => match create_habit(store, habit, checkmarks, today, import) {
+            Ok(id) => {
+                import.habits += 1;
+                id
+            }
+            Err(e) => {
+                import.rejected(&habit.name, e.to_string());
+                return;
+            }
+        },
+    };
+
+    let logged: HashSet<NaiveDate> = store_log
+        .list_by_habit(id)
+        .into_iter()
+        .map(|log_entry| log_entry.date)
+        .collect();
+    let (mut automatic, mut skipped) = (0, 0);
+    for (date, value) in checkmarks.iter().copied() {
+        let quantum = if habit.numerical {
+            value as f64 / LOOP_NUMERICAL_SCALE
+        } else {
+            match value {
+                LOOP_YES_MANUAL => 1.,
+                LOOP_YES_AUTO => {
+                    automatic += 1;
+                    continue;
+                }
+                LOOP_SKIP => {
+                    skipped += 1;
+                    continue;
+                }
+                _ => continue,
+            }
+        };
+        if quantum <= 0. || logged.contains(&date) {
+            continue;
+        }
+        match store_log.add(
+            HabitLog::new_dated(id, quantum, None, date),
+            store,
+            today,
+            false,
+        ) {
+            Ok(_) => import.logs += 1,
+            Err(e) => import.rejected(&habit.name, format!("{}: {}", date, e)),
+        }
+    }
+    if automatic > 0 {
+        import.unmapped(
+            &habit.name,
+            format!(
+                "days Loop checked by itself were left out ({}), habyt works them out from the schedule",
+                automatic
+            ),
+        );
+    }
+    if skipped > 0 {
+        import.unmapped(
+            &habit.name,
+            format!("skipped days were left out ({})", skipped),
+        );
+    }
+
+    if habit.archived && existing.is_none() {
+        store.delete_with(id, DeletePolicy::Archive, store_log);
+    }
+}
+
+fn create_habit(
+    store: &mut HabitStore,
+    habit: &LoopHabit,
+    checkmarks: &[(NaiveDate, i64)],
+    today: NaiveDate,
+    import: &mut LoopImport,
+) -> HabytResult<HabitId> {
+    let (quantum, unit) = if habit.numerical {
+        (habit.target, habit.unit.clone())
+    } else {
+        (1., String::new())
+    };
+    let kind = if habit.numerical && habit.at_most {
+        Kind::Negative
+    } else {
+        Kind::Positive
+    };
+    let notes = if habit.description.is_empty() {
+        None
+    } else {
+        Some(habit.description.clone())
+    };
+    let mut draft = HabitDraft::try_from(
+        habit.name.clone(),
+        quantum,
+        unit,
+        None,
+        None,
+        Some(kind),
+        notes,
+    )?;
+
+    let (times, days) = habit.frequency;
+    let from = checkmarks
+        .iter()
+        .map(|(date, _)| *date)
+        .min()
+        .unwrap_or(today);
+    let (streak, schedule, exact) = loop_frequency(times, days, from);
+    if !exact {
+        import.unmapped(
+            &habit.name,
+            format!(
+                "{} times in {} days has no equivalent, imported as {} times a {}",
+                times,
+                days,
+                match schedule {
+                    Some(Schedule::TimesPer(times)) => times,
+                    _ => 1,
+                },
+                streak.noun()
+            ),
+        );
+    }
+    draft = draft.with_schedule(streak, schedule);
+    if habit.archived {
+        import.unmapped(&habit.name, "archived in Loop, so archived here too".into());
+    }
+    if !habit.question.is_empty() {
+        import.unmapped(
+            &habit.name,
+            format!("the question '{}' was left out", habit.question),
+        );
+    }
+    if !habit.color.is_empty() {
+        import.unmapped(
+            &habit.name,
+            format!("the color {} was left out", habit.color),
+        );
+    }
+    let id = store.create(draft)?;
+    if from < today {
+        // Tracking began with the first checkmark, so the history isn't refused as too early
+        store.get_mut(id)?.created_at = Some(from.and_hms(0, 0, 0));
+    }
+    Ok(id)
+}
+
+/// The directory Loop keeps a habit's checkmarks and scores in, its position then its name,
+/// e.g. "001 Meditate"
+fn habit_dir(dir: &Path, habit: &LoopHabit) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .find(|path| {
+            let dir_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            dir_name.split_once(' ').is_some_and(|(position, name)| {
+                name == habit.name && position.chars().all(|c| c.is_ascii_digit())
+            })
+        })
+}
+
+/// A habit's own Checkmarks.csv: a date and a value a line, with or without a header
+fn read_checkmarks(file: &Path) -> HabytResult<Vec<(NaiveDate, i64)>> {
+    if !file.exists() {
+        return Ok(vec![]);
+    }
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(File::open(file)?);
+    let mut checkmarks = vec![];
+    for record in reader.records() {
+        let record = record?;
+        let date = record.get(0).and_then(|date| date.trim().parse().ok());
+        let value = record.get(1).and_then(|value| value.trim().parse().ok());
+        if let (Some(date), Some(value)) = (date, value) {
+            checkmarks.push((date, value));
+        }
+    }
+    Ok(checkmarks)
+}
+
+/// The Checkmarks.csv next to Habits.csv: a date a line, then a column per habit name
+fn read_shared_checkmarks(file: &Path) -> HabytResult<BTreeMap<String, Vec<(NaiveDate, i64)>>> {
+    let mut by_habit: BTreeMap<String, Vec<(NaiveDate, i64)>> = BTreeMap::new();
+    if !file.exists() {
+        return Ok(by_habit);
+    }
+    let mut reader = csv::Reader::from_reader(File::open(file)?);
+    let headers = reader.headers()?.clone();
+    for record in reader.records() {
+        let record = record?;
+        let date: NaiveDate = match record.get(0).and_then(|date| date.trim().parse().ok()) {
+            Some(date) => date,
+            None => continue,
+        };
+        for (name, value) in headers.iter().zip(record.iter()).skip(1) {
+            if let Ok(value) = value.trim().parse() {
+                by_habit
+                    .entry(name.trim().to_string())
+                    .or_default()
+                    .push((date, value));
+            }
+        }
+    }
+    Ok(by_habit)
+}
+
+#[cfg(test)]
+mod loop_import_tests {
+    use crate::loop_import::{import_loop, LoopImport};
+    use crate::models::{Habit, Kind, Schedule, Streak};
+    use crate::store::{HabitLogStore, HabitStore};
+    use crate::test_support::day;
+    use std::fs;
+    use std::path::Path;
+
+    /// The header of Habits.csv in newer Loop exports
+    const HABITS_HEADER: &str = "Position,Name,Type,Question,Description,FrequencyNumerator,\
+                                 FrequencyDenominator,Color,Unit,Target Type,Target Value,Archived?";
+
+    fn write(dir: &Path, file: &str, lines: &[&str]) {
+        fs::create_dir_all(dir.join(file).parent().unwrap()).unwrap();
+        fs::write(dir.join(file), lines.join("\n") + "\n").unwrap();
+    }
+
+    fn import(dir: &Path) -> (LoopImport, HabitStore, HabitLogStore) {
+        let mut store = HabitStore::new();
+        let mut store_log = HabitLogStore::new();
+        let import = import_loop(&mut store, &mut store_log, dir, day("2021-06-10"))
+            .expect("Failed to import the Loop export");
+        (import, store, store_log)
+    }
+
+    fn habit<'a>(store: &'a HabitStore, name: &str) -> &'a Habit {
+        store
+            .list()
+            .into_iter()
+            .find(|habit| habit.name.0 == name)
+            .expect("The habit was not imported")
+    }
+
+    #[test]
+    fn yes_no_habits_take_the_checkmarks_in_their_directory() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "Habits.csv",
+            &[
+                HABITS_HEADER,
+                "001,Meditate,YES_NO,Did you meditate today?,,3,7,#FF8F00,,AT_LEAST,0,false",
+            ],
+        );
+        write(
+            dir.path(),
+            "001 Meditate/Checkmarks.csv",
+            &["2021-06-03,2", "2021-06-02,1", "2021-06-01,2"],
+        );
+        write(dir.path(), "001 Meditate/Scores.csv", &["2021-06-03,0.5"]);
+
+        // act
+        let (import, store, store_log) = import(dir.path());
+
+        // assert
+        let meditate = habit(&store, "Meditate");
+        assert_eq!(meditate.streak, Streak::Weekly);
+        assert_eq!(meditate.schedule, Some(Schedule::TimesPer(3)));
+        assert_eq!(store_log.list_by_habit(meditate.id).len(), 2);
+        let notes: Vec<&str> = import.unmapped.iter().map(|n| n.note.as_str()).collect();
+        assert!(notes.iter().any(|note| note.contains("question")));
+        assert!(notes.iter().any(|note| note.contains("scores")));
+        assert!(notes
+            .iter()
+            .any(|note| note.contains("checked by itself were left out (1)")));
+    }
+
+    #[test]
+    fn numerical_habits_take_their_column_of_the_shared_checkmarks() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "Habits.csv",
+            &[
+                HABITS_HEADER,
+                "001,Coffee,NUMERICAL,How many cups?,,1,1,#FF8F00,cups,AT_MOST,2,false",
+            ],
+        );
+        write(
+            dir.path(),
+            "Checkmarks.csv",
+            &["Date,Coffee", "2021-06-02,3000", "2021-06-01,1500"],
+        );
+
+        // act
+        let (import, store, store_log) = import(dir.path());
+
+        // assert
+        assert_eq!((import.habits, import.logs), (1, 2));
+        let coffee = habit(&store, "Coffee");
+        assert_eq!(coffee.kind, Kind::Negative);
+        assert_eq!((coffee.quantum, coffee.unit.0.as_str()), (2., "cups"));
+        let mut cups: Vec<f64> = store_log
+            .list_by_habit(coffee.id)
+            .iter()
+            .map(|log_entry| log_entry.quantum)
+            .collect();
+        cups.sort_by(f64::total_cmp);
+        assert_eq!(cups, vec![1.5, 3.]);
+    }
+
+    #[test]
+    fn habits_named_unlike_habyt_allows_are_rejected() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "Habits.csv",
+            &[
+                HABITS_HEADER,
+                "001,A habit with a long name,YES_NO,,,1,1,,,AT_LEAST,0,false",
+            ],
+        );
+
+        // act
+        let (import, store, _) = import(dir.path());
+
+        // assert
+        assert_eq!(import.habits, 0);
+        assert_eq!(import.rejected[0].habit, "A habit with a long name");
+        assert!(store.list().is_empty());
+    }
+
+    #[test]
+    fn older_exports_say_how_often_as_repetitions_in_an_interval() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "Habits.csv",
+            &[
+                "Position,Name,Description,NumRepetitions,Interval,Color",
+                "001,Stretch,,1,2,#FF8F00",
+                "002,Swim,,2,30,#FF8F00",
+            ],
+        );
+
+        // act
+        let (import, store, _) = import(dir.path());
+
+        // assert
+        assert_eq!(import.habits, 2);
+        assert!(matches!(
+            habit(&store, "Stretch").schedule,
+            Some(Schedule::EveryNDays { days: 2, .. })
+        ));
+        let swim = habit(&store, "Swim");
+        assert_eq!(swim.streak, Streak::Monthly);
+        assert_eq!(swim.schedule, Some(Schedule::TimesPer(2)));
+    }
+
+    #[test]
+    fn frequencies_with_no_equivalent_are_imported_as_the_closest_and_reported() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "Habits.csv",
+            &[
+                HABITS_HEADER,
+                "001,Run,YES_NO,,,3,10,,,AT_LEAST,0,false",
+                "002,Read,YES_NO,,,4294967296000,3,,,AT_LEAST,0,false",
+                "003,Draw,YES_NO,,,4000000000,45,,,AT_LEAST,0,false",
+            ],
+        );
+
+        // act
+        let (import, store, _) = import(dir.path());
+
+        // assert
+        let run = habit(&store, "Run");
+        assert_eq!(run.streak, Streak::Monthly);
+        assert_eq!(run.schedule, Some(Schedule::TimesPer(9)));
+        assert!(import
+            .unmapped
+            .iter()
+            .any(|note| note.habit == "Run" && note.note.contains("3 times in 10 days")));
+        assert_eq!(habit(&store, "Read").schedule, None);
+        assert_eq!(habit(&store, "Draw").streak, Streak::Daily);
+    }
+
+    #[test]
+    fn archived_habits_are_archived_with_their_logs() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "Habits.csv",
+            &[HABITS_HEADER, "001,Juggle,YES_NO,,,1,1,,,AT_LEAST,0,true"],
+        );
+        write(
+            dir.path(),
+            "Checkmarks.csv",
+            &["Date,Juggle", "2021-06-01,2"],
+        );
+
+        // act
+        let (import, store, store_log) = import(dir.path());
+
+        // assert
+        assert_eq!((import.habits, import.logs), (1, 1));
+        assert!(store.list().is_empty());
+        assert_eq!(store.list_archived()[0].name.0, "Juggle");
+        assert_eq!(store_log.list_archived().len(), 1);
+        assert!(import
+            .unmapped
+            .iter()
+            .any(|note| note.note.contains("archived")));
+    }
+}
@@ -0,0 +1,258 @@
+use crate::error::{HabytError, HabytResult};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+
+/// Upgrades a store document from one version to the next
+pub(crate) type Migration = fn(Value) -> HabytResult<Value>;
+
+/// Migrations of `habit_store.yaml`, the one at index `n` upgrades version `n` to `n + 1`
+pub(crate) const HABIT_STORE_MIGRATIONS: &[Migration] =
+    &[wrap_in_envelope, add_archive, add_pauses, add_schedules];
+
+/// Migrations of `habit_log_store.yaml`, the one at index `n` upgrades version `n` to `n + 1`
+pub(crate) const HABIT_LOG_STORE_MIGRATIONS: &[Migration] =
+    &[wrap_in_envelope, give_logs_a_habit_id, add_archive];
+
+/// How a store is laid out on disk, so older files can be told apart and upgraded
+#[derive(Serialize)]
+struct Envelope<'a, T> {
+    version: u32,
+    store: &'a T,
+}
+
+#[derive(Deserialize)]
+struct OwnedEnvelope<T> {
+    store: T,
+}
+
+/// A store document, upgraded to the latest version if it was older
+pub(crate) struct Migrated<T> {
+    pub store: T,
+    /// The version the document had before, if it had to be upgraded
+    pub upgraded_from: Option<u32>,
+}
+
+/// The version written by this release of `habyt`
+pub(crate) fn latest_version(migrations: &[Migration]) -> u32 {
+    migrations.len() as u32
+}
+
+/// Serialize a store inside a versioned envelope
+pub(crate) fn to_versioned_yaml<T: Serialize>(
+    store: &T,
+    migrations: &[Migration],
+) -> HabytResult<String> {
+    Ok(serde_yaml::to_string(&Envelope {
+        version: latest_version(migrations),
+        store,
+    })?)
+}
+
+/// Parse a store document, running it through the migrations it has not seen yet
+pub(crate) fn from_versioned_yaml<T: DeserializeOwned>(
+    data: &str,
+    migrations: &[Migration],
+) -> HabytResult<Migrated<T>> {
+    let mut document: Value = serde_yaml::from_str(data)?;
+    let version = version_of(&document)?;
+    let latest = latest_version(migrations);
+    if version > latest {
+        return Err(HabytError::Conflict(format!(
+            "The store was written by a newer habyt (version {}, this one reads up to {})",
+            version, latest
+        )));
+    }
+
+    for migration in migrations[version as usize..].iter() {
+        document = migration(document)?;
+    }
+    let envelope: OwnedEnvelope<T> = serde_yaml::from_value(document)?;
+    Ok(Migrated {
+        store: envelope.store,
+        upgraded_from: if version < latest {
+            Some(version)
+        } else {
+            None
+        },
+    })
+}
+
+/// Files written before versioning have no `version` at all, and count as version 0
+fn version_of(document: &Value) -> HabytResult<u32> {
+    match document.get("version") {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .map(|version| version as u32)
+            .ok_or_else(|| HabytError::Parse("the store version is not a number".into())),
+    }
+}
+
+/// Version 0 to 1: the bare store moves into an envelope carrying the version
+fn wrap_in_envelope(document: Value) -> HabytResult<Value> {
+    let mut envelope = Mapping::new();
+    envelope.insert("version".into(), 1.into());
+    envelope.insert("store".into(), document);
+    Ok(Value::Mapping(envelope))
+}
+
+/// Version 1 to 2: log entries kept the habit id in `id`, which moves to `habit_id`, while
+/// `id` becomes the key the entry is stored under
+fn give_logs_a_habit_id(mut document: Value) -> HabytResult<Value> {
+    let entries = document
+        .get_mut("store")
+        .and_then(|store| store.get_mut("data"))
+        .and_then(Value::as_mapping_mut)
+        .ok_or_else(|| HabytError::Parse("the log store has no entries".into()))?;
+    for (key, entry) in entries.iter_mut() {
+        let entry = entry
+            .as_mapping_mut()
+            .ok_or_else(|| HabytError::Parse(format!("log {:?} is not an entry", key)))?;
+        let habit_id = entry.remove(&"id".into()).unwrap_or(Value::Null);
+        entry.insert("habit_id".into(), habit_id);
+        entry.insert("id".into(), key.clone());
+    }
+    if let Some(version) = document.get_mut("version") {
+        *version = 2.into();
+    }
+    Ok(document)
+}
+
+/// The stores gained an archive, which starts out empty. Nothing changes in the document,
+/// but an older `habyt` would drop the archive when saving, so it has to refuse these files.
+fn add_archive(document: Value) -> HabytResult<Value> {
+    bump_version(document)
+}
+
+/// Habit store version 2 to 3: habits gained pauses, none until a habit is paused
+fn add_pauses(document: Value) -> HabytResult<Value> {
+    bump_version(document)
+}
+
+/// Habit store version 3 to 4: habits gained a schedule, none for once every period
+fn add_schedules(document: Value) -> HabytResult<Value> {
+    bump_version(document)
+}
+
+/// For fields that default to empty, so the document stays as it is but gets a version an
+/// older `habyt`, which would drop them when saving, refuses
+fn bump_version(mut document: Value) -> HabytResult<Value> {
+    let version = version_of(&document)?;
+    if let Some(field) = document.get_mut("version") {
+        *field = (version + 1).into();
+    }
+    Ok(document)
+}
+
+#[cfg(test)]
+mod migrations_tests {
+    use crate::migrations::{
+        from_versioned_yaml, to_versioned_yaml, Migrated, HABIT_LOG_STORE_MIGRATIONS,
+        HABIT_STORE_MIGRATIONS,
+    };
+    use crate::models::HabitDraft;
+    use crate::store::{HabitLogStore, HabitStore};
+
+    #[test]
+    fn unversioned_stores_are_upgraded() {
+        // arrange
+        let mut habit_store = HabitStore::new();
+        habit_store
+            .create(HabitDraft::new("writing".into(), 750.0, "words".into()))
+            .expect("Failed to create habit.");
+        let unversioned = serde_yaml::to_string(&habit_store).unwrap();
+
+        // act
+        let migrated: Migrated<HabitStore> =
+            from_versioned_yaml(&unversioned, HABIT_STORE_MIGRATIONS)
+                .expect("Failed to migrate store.");
+
+        // assert
+        assert_eq!(migrated.upgraded_from, Some(0));
+        assert_eq!(migrated.store.list().len(), 1);
+    }
+
+    #[test]
+    fn current_stores_are_read_as_is() {
+        // arrange
+        let data = to_versioned_yaml(&HabitStore::new(), HABIT_STORE_MIGRATIONS).unwrap();
+
+        // act
+        let migrated: Migrated<HabitStore> =
+            from_versioned_yaml(&data, HABIT_STORE_MIGRATIONS).expect("Failed to read store.");
+
+        // assert
+        assert_eq!(migrated.upgraded_from, None);
+    }
+
+    #[test]
+    fn stores_from_a_newer_habyt_should_fail() {
+        // arrange
+        let data = "---\nversion: 99\nstore:\n  current_id: 0\n  data: {}\n";
+
+        // act
+        let migrated = from_versioned_yaml::<HabitStore>(data, HABIT_STORE_MIGRATIONS);
+
+        // assert
+        assert!(migrated.is_err());
+    }
+
+    #[test]
+    fn habits_from_before_pauses_and_schedules_are_upgraded_without_them() {
+        // arrange
+        let data = "---
+version: 2
+store:
+  current_id: 1
+  data:
+    1:
+      id: 1
+      name: writing
+      quantum: 750.0
+      unit: words
+      notes: \"\"
+      streak: Daily
+      difficulty: Easy
+      kind: Positive
+      active: true
+";
+
+        // act
+        let migrated: Migrated<HabitStore> =
+            from_versioned_yaml(data, HABIT_STORE_MIGRATIONS).expect("Failed to migrate store.");
+        let written = to_versioned_yaml(&migrated.store, HABIT_STORE_MIGRATIONS).unwrap();
+
+        // assert
+        assert_eq!(migrated.upgraded_from, Some(2));
+        let habit = migrated.store.get(1).unwrap();
+        assert!(habit.pauses.is_empty());
+        assert_eq!(habit.schedule, None);
+        assert!(written.contains("version: 4"));
+    }
+
+    #[test]
+    fn log_entries_keep_their_habit_when_upgraded() {
+        // arrange
+        let data = "---
+version: 1
+store:
+  current_id: 4
+  data:
+    4:
+      id: 2
+      quantum: 750.0
+      notes: \"\"
+      date: 2021-06-01
+";
+
+        // act
+        let migrated: Migrated<HabitLogStore> =
+            from_versioned_yaml(data, HABIT_LOG_STORE_MIGRATIONS).expect("Failed to migrate logs.");
+
+        // assert
+        let log_entry = migrated.store.get(4).expect("Log entry went missing.");
+        assert_eq!(log_entry.id, 4);
+        assert_eq!(log_entry.habit_id, 2);
+    }
+}
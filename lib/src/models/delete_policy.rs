@@ -0,0 +1,29 @@
+use crate::error::HabytError;
+use std::str::FromStr;
+
+/// What happens to the logs of a habit when it's deleted
+#[derive(PartialEq, Debug, Copy, Clone, Eq)]
+pub enum DeletePolicy {
+    /// Delete the logs along with the habit
+    Cascade,
+    /// Keep the logs, and a `Tombstone` of the habit for them to point at
+    Tombstone,
+    /// Move both the habit and its logs to the archive
+    Archive,
+}
+
+impl FromStr for DeletePolicy {
+    type Err = HabytError;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy {
+            "cascade" => Ok(DeletePolicy::Cascade),
+            "tombstone" => Ok(DeletePolicy::Tombstone),
+            "archive" => Ok(DeletePolicy::Archive),
+            _ => Err(HabytError::validation(&format!(
+                "'{}' is not a way to delete logs, try cascade, tombstone or archive",
+                policy
+            ))),
+        }
+    }
+}
@@ -1,9 +1,11 @@
-use crate::models::{HabitId, HabitName, HabitUnit};
-use serde::__private::fmt::Error;
-use serde::__private::Formatter;
+use crate::error::{HabytError, HabytResult};
+use crate::models::{HabitId, HabitName, HabitUnit, Pause, Schedule};
+use chrono::naive::MAX_DATE;
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
+use std::fmt::{Error, Formatter};
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct Habit {
     pub id: HabitId,
     pub name: HabitName,
@@ -14,6 +16,19 @@ pub struct Habit {
     pub difficulty: Difficulty,
     pub kind: Kind,
     pub active: bool,
+    /// When the habit was first tracked, unknown for habits created before it was recorded
+    #[serde(default)]
+    pub created_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub updated_at: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub archived_at: Option<NaiveDateTime>,
+    /// When the habit was set aside, oldest first
+    #[serde(default)]
+    pub pauses: Vec<Pause>,
+    /// When the habit is due within its `streak`, none for once every period
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
 }
 
 impl std::fmt::Display for Habit {
@@ -34,13 +49,22 @@ pub enum Streak {
     Monthly,
 }
 impl Streak {
-    pub fn to_string(&self) -> &str {
-        match *self {
+    pub fn to_string(self) -> &'static str {
+        match self {
             Streak::Daily => "Daily",
             Streak::Weekly => "Weekly",
             Streak::Monthly => "Monthly",
         }
     }
+
+    /// The period a streak counts in, e.g. "week" for `Streak::Weekly`
+    pub fn noun(self) -> &'static str {
+        match self {
+            Streak::Daily => "day",
+            Streak::Weekly => "week",
+            Streak::Monthly => "month",
+        }
+    }
 }
 
 #[derive(PartialEq, Debug, Copy, Clone, Hash, Eq, Serialize, Deserialize)]
@@ -51,8 +75,8 @@ pub enum Difficulty {
     Hard,
 }
 impl Difficulty {
-    fn to_string(&self) -> &str {
-        match *self {
+    fn to_string(self) -> &'static str {
+        match self {
             Difficulty::Trivial => "Trivial",
             Difficulty::Easy => "Easy",
             Difficulty::Medium => "Medium",
@@ -67,8 +91,8 @@ pub enum Kind {
     Negative,
 }
 impl Kind {
-    fn to_string(&self) -> &str {
-        match *self {
+    fn to_string(self) -> &'static str {
+        match self {
             Kind::Positive => "Positive",
             Kind::Negative => "Negative",
         }
@@ -77,6 +101,7 @@ impl Kind {
 
 impl Habit {
     // as well as to the methods...
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: HabitId,
         name: HabitName,
@@ -87,7 +112,23 @@ impl Habit {
         kind: Option<Kind>,
         notes: Option<String>,
     ) -> Self {
-        Self {
+        Habit::try_new(id, name, quantum, unit, streak, difficulty, kind, notes)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like `Habit::new`, but reports overly long notes instead of panicking
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_new(
+        id: HabitId,
+        name: HabitName,
+        quantum: f64,
+        unit: HabitUnit,
+        streak: Option<Streak>,
+        difficulty: Option<Difficulty>,
+        kind: Option<Kind>,
+        notes: Option<String>,
+    ) -> HabytResult<Self> {
+        Ok(Self {
             id,
             name,
             quantum: if quantum.is_sign_positive() {
@@ -114,13 +155,21 @@ impl Habit {
             },
             notes: match notes {
                 Some(n) if n.len() > 280 => {
-                    panic!("Habit's note cannot be longer than {} characters!", 280)
+                    return Err(HabytError::validation(&format!(
+                        "Habit's note cannot be longer than {} characters!",
+                        280
+                    )))
                 }
                 Some(n) => n,
                 None => "".into(),
             },
             active: true,
-        }
+            created_at: None,
+            updated_at: None,
+            archived_at: None,
+            pauses: vec![],
+            schedule: None,
+        })
     }
 }
 
@@ -140,14 +189,11 @@ impl Habit {
 // or, more getters
 impl Habit {
     pub fn is_easy(&self) -> bool {
-        match self.difficulty {
-            Difficulty::Easy => true,
-            _ => false,
-        }
+        matches!(self.difficulty, Difficulty::Easy)
     }
 }
 
-trait HasPosNeg {
+pub trait HasPosNeg {
     fn is_positive(&self) -> bool;
     fn is_negative(&self) -> bool;
 }
@@ -164,6 +210,43 @@ impl HasPosNeg for Habit {
     }
 }
 
+impl Habit {
+    /// The day the habit started being tracked, if known
+    pub fn tracked_since(&self) -> Option<NaiveDate> {
+        self.created_at.map(|created_at| created_at.date())
+    }
+
+    /// How many days the habit has been tracked for, counting `today`
+    pub fn days_tracked(&self, today: NaiveDate) -> Option<i64> {
+        self.tracked_since()
+            .map(|since| (today - since).num_days().max(0) + 1)
+    }
+}
+
+impl Habit {
+    /// Whether the habit is due on `date` according to its schedule.
+    ///
+    /// Habits without one can be done on any day of their period, and so can habits done a
+    /// number of times per period.
+    pub fn is_due_on(&self, date: NaiveDate) -> bool {
+        self.schedule
+            .as_ref()
+            .is_none_or(|schedule| schedule.is_due_on(date))
+    }
+
+    /// Whether logging `quantum` within a period keeps up with the habit.
+    ///
+    /// A positive habit's quantum is a goal to reach, a negative habit's quantum is a ceiling
+    /// to stay at or below, so "cigarettes, 5" is met by smoking 5 or fewer.
+    pub fn is_met(&self, quantum: f64) -> bool {
+        if self.is_negative() {
+            quantum <= self.quantum
+        } else {
+            quantum >= self.quantum
+        }
+    }
+}
+
 impl Habit {
     pub fn activate(&mut self) {
         self.active = true
@@ -178,5 +261,94 @@ impl Habit {
     }
 }
 
+impl Habit {
+    /// Set the habit aside from `from`, up to and including `until` or until it's resumed.
+    ///
+    /// A habit paused without an end is deactivated, and the days from `from` on can't be logged
+    /// until it's resumed.
+    pub fn pause(&mut self, from: NaiveDate, until: Option<NaiveDate>) -> HabytResult<()> {
+        if until.is_some_and(|until| until < from) {
+            return Err(HabytError::validation(&format!(
+                "A pause can't end before it starts on {}",
+                from
+            )));
+        }
+        if self
+            .pauses
+            .iter()
+            .any(|pause| pause.overlaps(from, until.unwrap_or(MAX_DATE)))
+        {
+            return Err(HabytError::Conflict(format!(
+                "{} is already paused then",
+                self.name
+            )));
+        }
+        self.pauses.push(Pause { from, until });
+        self.pauses.sort_by_key(|pause| pause.from);
+        if until.is_none() {
+            self.deactivate();
+        }
+        Ok(())
+    }
+
+    /// Pick the habit back up on `today`, ending the pause it's in
+    pub fn resume(&mut self, today: NaiveDate) -> HabytResult<()> {
+        let paused = self.pauses.iter().position(|pause| pause.covers(today));
+        let planned = self.pauses.iter().any(|pause| pause.from > today);
+        if paused.is_none() && !planned && self.active {
+            return Err(HabytError::Conflict(format!("{} is not paused", self.name)));
+        }
+
+        if let Some(index) = paused {
+            if self.pauses[index].from == today {
+                self.pauses.remove(index);
+            } else {
+                self.pauses[index].until = Some(today.pred());
+            }
+        }
+        // Pauses planned for later are called off too
+        self.pauses.retain(|pause| pause.from <= today);
+        self.activate();
+        Ok(())
+    }
+
+    pub fn is_paused_on(&self, date: NaiveDate) -> bool {
+        self.pauses.iter().any(|pause| pause.covers(date))
+    }
+
+    /// Whether the habit was paused on any day from `start` to `end`
+    pub fn is_paused_during(&self, start: NaiveDate, end: NaiveDate) -> bool {
+        self.pauses.iter().any(|pause| pause.overlaps(start, end))
+    }
+}
+
 #[derive(PartialEq, Debug)]
 pub struct DeletedHabit(pub Habit);
+
+#[cfg(test)]
+mod habit_tests {
+    use crate::models::{Kind, Streak};
+    use crate::test_support::{day, habit};
+
+    #[test]
+    fn resuming_a_paused_habit_ends_its_pause_the_day_before() {
+        // arrange
+        let mut habit = habit(Streak::Daily, 5., Kind::Positive);
+        habit
+            .pause(day("2021-06-01"), None)
+            .expect("Failed to pause habit.");
+
+        // act
+        let paused_twice = habit.pause(day("2021-06-03"), Some(day("2021-06-04")));
+        habit
+            .resume(day("2021-06-10"))
+            .expect("Failed to resume habit.");
+
+        // assert
+        assert!(paused_twice.is_err());
+        assert!(habit.active);
+        assert!(habit.is_paused_on(day("2021-06-09")));
+        assert!(!habit.is_paused_on(day("2021-06-10")));
+        assert!(habit.resume(day("2021-06-11")).is_err());
+    }
+}
@@ -1,4 +1,5 @@
-use crate::models::{Difficulty, HabitName, HabitUnit, Kind, Streak};
+use crate::error::HabytResult;
+use crate::models::{Difficulty, HabitName, HabitUnit, Kind, Schedule, Streak};
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct HabitDraft {
@@ -9,20 +10,18 @@ pub struct HabitDraft {
     pub difficulty: Option<Difficulty>,
     pub kind: Option<Kind>,
     pub notes: Option<String>,
+    pub schedule: Option<Schedule>,
 }
 
 impl HabitDraft {
     // as well as to the methods...
     pub fn new(name: String, quantum: f64, unit: String) -> Self {
-        Self {
-            name: HabitName::new(name).expect("Habit name should exist"),
-            quantum,
-            unit: HabitUnit::new(unit).expect("Habit unit should exist"),
-            streak: None,
-            difficulty: None,
-            kind: None,
-            notes: None,
-        }
+        HabitDraft::try_new(name, quantum, unit).expect("Habit name and unit should be valid")
+    }
+
+    /// Like `HabitDraft::new`, but reports an invalid name or unit instead of panicking
+    pub fn try_new(name: String, quantum: f64, unit: String) -> HabytResult<Self> {
+        HabitDraft::try_from(name, quantum, unit, None, None, None, None)
     }
 
     pub fn from(
@@ -34,14 +33,36 @@ impl HabitDraft {
         kind: Option<Kind>,
         notes: Option<String>,
     ) -> Self {
-        Self {
-            name: HabitName::new(name).expect("Habit name should exist"),
+        HabitDraft::try_from(name, quantum, unit, streak, difficulty, kind, notes)
+            .expect("Habit name and unit should be valid")
+    }
+
+    /// Like `HabitDraft::from`, but reports an invalid name or unit instead of panicking
+    pub fn try_from(
+        name: String,
+        quantum: f64,
+        unit: String,
+        streak: Option<Streak>,
+        difficulty: Option<Difficulty>,
+        kind: Option<Kind>,
+        notes: Option<String>,
+    ) -> HabytResult<Self> {
+        Ok(Self {
+            name: HabitName::new(name)?,
             quantum,
-            unit: HabitUnit::new(unit).expect("Habit unit should exist"),
+            unit: HabitUnit::new(unit)?,
             streak,
             difficulty,
             kind,
             notes,
-        }
+            schedule: None,
+        })
+    }
+
+    /// Count periods of `streak`, when the habit is due as `schedule` says, see `parse_schedule`
+    pub fn with_schedule(mut self, streak: Streak, schedule: Option<Schedule>) -> Self {
+        self.streak = Some(streak);
+        self.schedule = schedule;
+        self
     }
 }
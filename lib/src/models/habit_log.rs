@@ -1,11 +1,16 @@
 extern crate chrono;
 
+use crate::error::{HabytError, HabytResult};
+use crate::models::HabitId;
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct HabitLog {
+    /// Assigned by `HabitLogStore::add`, 0 until then
     pub id: u32,
+    /// The habit this entry was logged against
+    pub habit_id: HabitId,
     pub quantum: f64,
     pub notes: String,
     pub date: NaiveDate,
@@ -13,18 +18,75 @@ pub struct HabitLog {
 
 impl HabitLog {
     // as well as to the methods...
-    pub fn new(id: u32, quantum: f64, notes: Option<String>) -> Self {
-        Self {
-            id,
+    pub fn new(habit_id: HabitId, quantum: f64, notes: Option<String>) -> Self {
+        HabitLog::try_new(habit_id, quantum, notes).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like `HabitLog::new`, but reports overly long notes instead of panicking
+    pub fn try_new(habit_id: HabitId, quantum: f64, notes: Option<String>) -> HabytResult<Self> {
+        HabitLog::try_new_dated(habit_id, quantum, notes, Local::today().naive_local())
+    }
+
+    /// A log entry for some other day than today, e.g. a forgotten run from yesterday
+    pub fn new_dated(
+        habit_id: HabitId,
+        quantum: f64,
+        notes: Option<String>,
+        date: NaiveDate,
+    ) -> Self {
+        HabitLog::try_new_dated(habit_id, quantum, notes, date).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like `HabitLog::new_dated`, but reports overly long notes and quantums that aren't
+    /// numbers instead of panicking
+    pub fn try_new_dated(
+        habit_id: HabitId,
+        quantum: f64,
+        notes: Option<String>,
+        date: NaiveDate,
+    ) -> HabytResult<Self> {
+        validate_quantum(quantum)?;
+        Ok(Self {
+            id: 0,
+            habit_id,
             quantum,
             notes: match notes {
                 Some(n) if n.len() > 280 => {
-                    panic!("Log's note cannot be longer than {} characters!", 280)
+                    return Err(HabytError::validation(&format!(
+                        "Log's note cannot be longer than {} characters!",
+                        280
+                    )))
                 }
                 Some(n) => n,
                 None => "".into(),
             },
-            date: Local::today().naive_local(),
+            date,
+        })
+    }
+}
+
+/// A logged quantum has to be a number, NaN and the infinities can't be added up
+pub(crate) fn validate_quantum(quantum: f64) -> HabytResult<()> {
+    if quantum.is_finite() {
+        Ok(())
+    } else {
+        Err(HabytError::validation(&format!(
+            "'{}' can't be logged, the quantum has to be a number",
+            quantum
+        )))
+    }
+}
+
+#[cfg(test)]
+mod habit_log_tests {
+    use crate::models::HabitLog;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn logging_a_quantum_that_is_not_a_number_should_fail() {
+        let date = NaiveDate::from_ymd(2021, 6, 7);
+        for quantum in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(HabitLog::try_new_dated(1, quantum, None, date).is_err());
         }
     }
 }
@@ -0,0 +1,23 @@
+use chrono::NaiveDate;
+
+/// Changes to a log entry, the fields left at `None` stay as they are
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct HabitLogPatch {
+    pub quantum: Option<f64>,
+    pub notes: Option<String>,
+    pub date: Option<NaiveDate>,
+}
+
+impl HabitLogPatch {
+    pub fn new(quantum: Option<f64>, notes: Option<String>, date: Option<NaiveDate>) -> Self {
+        Self {
+            quantum,
+            notes,
+            date,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quantum.is_none() && self.notes.is_none() && self.date.is_none()
+    }
+}
@@ -9,7 +9,7 @@ impl HabitName {
     pub fn new(name: String) -> Result<HabitName, ValidationError> {
         if name.is_empty() {
             Err(ValidationError::new("Habit's Name cannot be empty!"))
-        } else if name.trim().split_whitespace().count() > 3 {
+        } else if name.split_whitespace().count() > 3 {
             Err(ValidationError::new(
                 "Habit's name cannot have more than 3 words!!",
             ))
@@ -1,4 +1,5 @@
-use crate::models::{Difficulty, HabitName, HabitUnit, Kind, Streak};
+use crate::error::HabytResult;
+use crate::models::{Difficulty, HabitName, HabitUnit, Kind, Schedule, Streak};
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct HabitPatch {
@@ -9,6 +10,8 @@ pub struct HabitPatch {
     pub difficulty: Option<Difficulty>,
     pub kind: Option<Kind>,
     pub notes: Option<String>,
+    /// A new schedule, which may be none at all to go back to once every period
+    pub schedule: Option<Option<Schedule>>,
 }
 
 impl HabitPatch {
@@ -21,22 +24,44 @@ impl HabitPatch {
         kind: Option<Kind>,
         notes: Option<String>,
     ) -> Self {
-        Self {
+        HabitPatch::try_from(name, quantum, unit, streak, difficulty, kind, notes)
+            .expect("Habit name and unit must be valid")
+    }
+
+    /// Like `HabitPatch::from`, but reports an invalid name or unit instead of panicking
+    pub fn try_from(
+        name: String,
+        quantum: f64,
+        unit: String,
+        streak: Option<Streak>,
+        difficulty: Option<Difficulty>,
+        kind: Option<Kind>,
+        notes: Option<String>,
+    ) -> HabytResult<Self> {
+        Ok(Self {
             name: if name.is_empty() {
                 None
             } else {
-                Some(HabitName::new(name).expect("Habit name must be valid"))
+                Some(HabitName::new(name)?)
             },
             quantum: Some(quantum),
             unit: if unit.is_empty() {
                 None
             } else {
-                Some(HabitUnit::new(unit).expect("Habit unit must be valid"))
+                Some(HabitUnit::new(unit)?)
             },
             streak,
             difficulty,
             kind,
             notes,
-        }
+            schedule: None,
+        })
+    }
+
+    /// Count periods of `streak`, when the habit is due as `schedule` says, see `parse_schedule`
+    pub fn with_schedule(mut self, streak: Streak, schedule: Option<Schedule>) -> Self {
+        self.streak = Some(streak);
+        self.schedule = Some(schedule);
+        self
     }
 }
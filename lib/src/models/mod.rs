@@ -1,20 +1,30 @@
 /// This file has mysteriously existed for so lang, and we never quite discussed about it
 ///
 /// Here's we using the `habit` recognized by the module name as well as a matching filename relative to this file
+mod delete_policy;
 mod habit;
 mod habit_draft;
 mod habit_id;
 mod habit_log;
+mod habit_log_patch;
 mod habit_name;
 mod habit_patch;
 mod habit_unit;
+mod pause;
+mod schedule;
+mod tombstone;
 mod validation_error;
 
+pub use delete_policy::*;
 pub use habit::*;
 pub use habit_draft::*;
 pub use habit_id::*;
 pub use habit_log::*;
+pub use habit_log_patch::*;
 pub use habit_name::*;
 pub use habit_patch::*;
 pub use habit_unit::*;
+pub use pause::*;
+pub use schedule::*;
+pub use tombstone::*;
 pub use validation_error::*;
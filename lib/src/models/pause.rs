@@ -0,0 +1,21 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// Days a habit was set aside, e.g. for a vacation or an illness
+#[derive(Serialize, Deserialize, PartialEq, Debug, Copy, Clone)]
+pub struct Pause {
+    pub from: NaiveDate,
+    /// The last day of the pause, none while it lasts until the habit is resumed
+    pub until: Option<NaiveDate>,
+}
+
+impl Pause {
+    pub fn covers(&self, date: NaiveDate) -> bool {
+        self.from <= date && self.until.is_none_or(|until| date <= until)
+    }
+
+    /// Whether any day from `start` to `end` falls within the pause
+    pub fn overlaps(&self, start: NaiveDate, end: NaiveDate) -> bool {
+        self.from <= end && self.until.is_none_or(|until| start <= until)
+    }
+}
@@ -0,0 +1,158 @@
+use crate::error::{HabytError, HabytResult};
+use crate::models::Streak;
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+/// When a habit is due, beyond once every period of its `Streak`
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum Schedule {
+    /// On these days of the week only, e.g. Mon/Wed/Fri
+    Weekdays(Vec<Weekday>),
+    /// On this many different days within each period of the habit's `Streak`, e.g. 3 times a week
+    TimesPer(u32),
+    /// Every this many days, counting from `from`; never due if it's 0, which only a
+    /// hand-edited store can hold
+    EveryNDays { days: u32, from: NaiveDate },
+}
+
+impl Schedule {
+    /// Whether the habit is due on `date`; habits done a few times per period can be done any day
+    pub fn is_due_on(&self, date: NaiveDate) -> bool {
+        match self {
+            Schedule::Weekdays(weekdays) => weekdays.contains(&date.weekday()),
+            Schedule::TimesPer(_) => true,
+            Schedule::EveryNDays { days, from } => {
+                date >= *from && (date - *from).num_days().checked_rem(*days as i64) == Some(0)
+            }
+        }
+    }
+}
+
+/// Read a schedule the way people type it on the command line.
+///
+/// Accepts `daily`, `weekly` and `monthly`, days of the week such as `mon,wed,fri`, a number
+/// of times per period such as `3/week`, and intervals such as `every 2 days`, which start
+/// counting from `today`. Returns the `Streak` to count periods in along with the schedule.
+pub fn parse_schedule(input: &str, today: NaiveDate) -> HabytResult<(Streak, Option<Schedule>)> {
+    let input = input.trim().to_lowercase();
+    let invalid = || {
+        HabytError::validation(&format!(
+            "'{}' is not a schedule, try daily, mon,wed,fri, 3/week or every 2 days",
+            input
+        ))
+    };
+
+    match input.as_str() {
+        "daily" => return Ok((Streak::Daily, None)),
+        "weekly" => return Ok((Streak::Weekly, None)),
+        "monthly" => return Ok((Streak::Monthly, None)),
+        _ => {}
+    }
+
+    if let Some((times, per)) = input.split_once('/') {
+        let times: u32 = times.trim().parse().map_err(|_| invalid())?;
+        let streak = match per.trim() {
+            "week" => Streak::Weekly,
+            "month" => Streak::Monthly,
+            _ => return Err(invalid()),
+        };
+        if times == 0 {
+            return Err(invalid());
+        }
+        return Ok((streak, Some(Schedule::TimesPer(times))));
+    }
+
+    if let Some(interval) = input.strip_prefix("every ") {
+        let days = match interval.split_whitespace().collect::<Vec<_>>()[..] {
+            ["day"] => 1,
+            [days, "days"] => days.parse().map_err(|_| invalid())?,
+            _ => return Err(invalid()),
+        };
+        if days == 0 {
+            return Err(invalid());
+        }
+        return Ok((
+            Streak::Daily,
+            Some(Schedule::EveryNDays { days, from: today }),
+        ));
+    }
+
+    let mut weekdays = input
+        .split(',')
+        .map(|day| day.trim().parse::<Weekday>().map_err(|_| invalid()))
+        .collect::<HabytResult<Vec<_>>>()?;
+    weekdays.sort_by_key(|day| day.num_days_from_monday());
+    weekdays.dedup();
+    Ok((Streak::Daily, Some(Schedule::Weekdays(weekdays))))
+}
+
+#[cfg(test)]
+mod schedule_tests {
+    use crate::models::{parse_schedule, Schedule, Streak};
+    use crate::test_support::day;
+    use chrono::Weekday;
+
+    #[test]
+    fn schedules_are_understood() {
+        let today = day("2021-06-10");
+
+        assert_eq!(
+            parse_schedule("weekly", today).unwrap(),
+            (Streak::Weekly, None)
+        );
+        assert_eq!(
+            parse_schedule("Fri,mon, wed", today).unwrap(),
+            (
+                Streak::Daily,
+                Some(Schedule::Weekdays(vec![
+                    Weekday::Mon,
+                    Weekday::Wed,
+                    Weekday::Fri
+                ]))
+            )
+        );
+        assert_eq!(
+            parse_schedule("3/week", today).unwrap(),
+            (Streak::Weekly, Some(Schedule::TimesPer(3)))
+        );
+        assert_eq!(
+            parse_schedule("every 2 days", today).unwrap(),
+            (
+                Streak::Daily,
+                Some(Schedule::EveryNDays {
+                    days: 2,
+                    from: today
+                })
+            )
+        );
+        assert!(parse_schedule("0/week", today).is_err());
+        assert!(parse_schedule("whenever", today).is_err());
+    }
+
+    #[test]
+    fn habits_are_due_on_the_scheduled_days_only() {
+        // 2021-06-07 is a Monday
+        let weekdays = Schedule::Weekdays(vec![Weekday::Mon, Weekday::Fri]);
+        let every_other_day = Schedule::EveryNDays {
+            days: 2,
+            from: day("2021-06-07"),
+        };
+
+        assert!(weekdays.is_due_on(day("2021-06-07")));
+        assert!(!weekdays.is_due_on(day("2021-06-08")));
+        assert!(every_other_day.is_due_on(day("2021-06-09")));
+        assert!(!every_other_day.is_due_on(day("2021-06-10")));
+        assert!(!every_other_day.is_due_on(day("2021-06-05")));
+    }
+
+    #[test]
+    fn an_interval_of_zero_days_is_never_due() {
+        let never = Schedule::EveryNDays {
+            days: 0,
+            from: day("2021-06-07"),
+        };
+
+        assert!(!never.is_due_on(day("2021-06-07")));
+        assert!(!never.is_due_on(day("2021-06-08")));
+    }
+}
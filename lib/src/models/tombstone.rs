@@ -0,0 +1,12 @@
+use crate::models::{HabitId, HabitName};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// What's left of a habit deleted without its logs, so they still tell what they were for
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct Tombstone {
+    pub id: HabitId,
+    /// Unknown when the habit was already gone before its logs were noticed by `habyt doctor`
+    pub name: Option<HabitName>,
+    pub deleted_at: NaiveDateTime,
+}
@@ -0,0 +1,496 @@
+use crate::csv_io::CsvImport;
+use crate::error::{HabytError, HabytResult};
+use crate::heatmap::{HeatmapWeek, Shade};
+use crate::loop_import::LoopImport;
+use crate::models::{Habit, HabitId, HabitLog, HabitName, HabitUnit, Kind, Schedule, Streak};
+use crate::progress::{Progress, ProgressStatus};
+use crate::stats::{HabitStats, Trend};
+use crate::streak::StreakSummary;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Serialize;
+use std::io::Write;
+use std::str::FromStr;
+
+/// How a command writes what it has to say
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum OutputFormat {
+    /// Sentences for people to read
+    Table,
+    Json,
+    Yaml,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = HabytError;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "yaml" => Ok(OutputFormat::Yaml),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(HabytError::validation(&format!(
+                "'{}' is not an output format, try table, json, yaml or csv",
+                format
+            ))),
+        }
+    }
+}
+
+/// Write `records` to `out` as a JSON array, a YAML sequence or CSV rows under a header.
+///
+/// The table format is made of sentences each command writes for itself, so it's refused here.
+pub fn write_records<T: Serialize, W: Write>(
+    records: &[T],
+    format: OutputFormat,
+    mut out: W,
+) -> HabytResult<()> {
+    match format {
+        OutputFormat::Table => {
+            return Err(HabytError::validation(
+                "Records are only written as json, yaml or csv",
+            ))
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, records)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Yaml => serde_yaml::to_writer(&mut out, records)?,
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for record in records {
+                writer.serialize(record)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// A habit as written by `--format`
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct HabitRecord {
+    pub id: HabitId,
+    pub name: HabitName,
+    pub quantum: f64,
+    pub unit: HabitUnit,
+    pub streak: Streak,
+    pub kind: Kind,
+    /// When the habit is due, the way `--schedule` takes it, e.g. "daily" or "mon,wed,fri"
+    pub schedule: String,
+    pub active: bool,
+    pub paused: bool,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    pub archived_at: Option<NaiveDateTime>,
+    /// Only known where the logs were looked at, e.g. by `habyt list`
+    pub current_streak: Option<u32>,
+    pub longest_streak: Option<u32>,
+}
+
+impl HabitRecord {
+    pub fn new(habit: &Habit, today: NaiveDate) -> Self {
+        HabitRecord {
+            id: habit.id,
+            name: habit.name.clone(),
+            quantum: habit.quantum,
+            unit: habit.unit.clone(),
+            streak: habit.streak,
+            kind: habit.kind,
+            schedule: schedule_spec(habit),
+            active: habit.active,
+            paused: habit.is_paused_on(today),
+            created_at: habit.created_at,
+            updated_at: habit.updated_at,
+            archived_at: habit.archived_at,
+            current_streak: None,
+            longest_streak: None,
+        }
+    }
+
+    pub fn with_streak(mut self, streak: &StreakSummary) -> Self {
+        self.current_streak = Some(streak.current);
+        self.longest_streak = Some(streak.longest);
+        self
+    }
+}
+
+/// A habit's schedule the way `parse_schedule` reads it
+pub(crate) fn schedule_spec(habit: &Habit) -> String {
+    match &habit.schedule {
+        None => habit.streak.to_string().to_lowercase(),
+        Some(Schedule::Weekdays(weekdays)) => weekdays
+            .iter()
+            .map(|day| day.to_string().to_lowercase())
+            .collect::<Vec<_>>()
+            .join(","),
+        Some(Schedule::TimesPer(times)) => format!("{}/{}", times, habit.streak.noun()),
+        Some(Schedule::EveryNDays { days, .. }) => format!("every {} days", days),
+    }
+}
+
+/// A log entry as written by `--format`
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct LogRecord {
+    pub id: u32,
+    pub habit_id: HabitId,
+    /// Empty when the habit is gone without a name to remember it by
+    pub habit_name: Option<HabitName>,
+    pub quantum: f64,
+    pub notes: String,
+    pub date: NaiveDate,
+}
+
+impl LogRecord {
+    pub fn new(log_entry: &HabitLog, habit_name: Option<&HabitName>) -> Self {
+        LogRecord {
+            id: log_entry.id,
+            habit_id: log_entry.habit_id,
+            habit_name: habit_name.cloned(),
+            quantum: log_entry.quantum,
+            notes: log_entry.notes.clone(),
+            date: log_entry.date,
+        }
+    }
+}
+
+/// Where a habit stands today, as written by `habyt today --format`
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct ProgressRecord {
+    pub habit_id: HabitId,
+    pub habit_name: HabitName,
+    pub status: ProgressStatus,
+    pub logged: f64,
+    pub quantum: f64,
+    pub remaining: f64,
+    pub unit: HabitUnit,
+    /// Only for habits done a number of times per period
+    pub times_done: Option<u32>,
+    pub times_needed: Option<u32>,
+    pub current_streak: u32,
+}
+
+impl ProgressRecord {
+    pub fn new(habit: &Habit, progress: &Progress, streak: &StreakSummary) -> Self {
+        ProgressRecord {
+            habit_id: habit.id,
+            habit_name: habit.name.clone(),
+            status: progress.status,
+            logged: progress.logged,
+            quantum: habit.quantum,
+            remaining: progress.remaining,
+            unit: habit.unit.clone(),
+            times_done: progress.times.map(|(done, _)| done),
+            times_needed: progress.times.map(|(_, needed)| needed),
+            current_streak: streak.current,
+        }
+    }
+}
+
+/// The statistics of a habit, as written by `habyt stats --format`
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct StatsRecord {
+    pub habit_id: HabitId,
+    pub habit_name: HabitName,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub periods: u32,
+    pub completed: u32,
+    pub completion_rate: f64,
+    pub total_quantum: f64,
+    pub average_per_period: f64,
+    pub best_week: Option<NaiveDate>,
+    pub best_week_quantum: Option<f64>,
+    pub worst_week: Option<NaiveDate>,
+    pub worst_week_quantum: Option<f64>,
+    pub longest_gap_from: Option<NaiveDate>,
+    pub longest_gap_to: Option<NaiveDate>,
+    pub trend: Trend,
+}
+
+impl StatsRecord {
+    pub fn new(habit: &Habit, stats: &HabitStats) -> Self {
+        StatsRecord {
+            habit_id: habit.id,
+            habit_name: habit.name.clone(),
+            from: stats.range.start,
+            to: stats.range.end,
+            periods: stats.periods,
+            completed: stats.completed,
+            completion_rate: stats.completion_rate,
+            total_quantum: stats.total_quantum,
+            average_per_period: stats.average_per_period,
+            best_week: stats.best_week.map(|(week, _)| week.start),
+            best_week_quantum: stats.best_week.map(|(_, quantum)| quantum),
+            worst_week: stats.worst_week.map(|(week, _)| week.start),
+            worst_week_quantum: stats.worst_week.map(|(_, quantum)| quantum),
+            longest_gap_from: stats.longest_gap.map(|gap| gap.start),
+            longest_gap_to: stats.longest_gap.map(|gap| gap.end),
+            trend: stats.trend,
+        }
+    }
+}
+
+/// A day of a heatmap, as written by `habyt heatmap --format`
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct HeatmapDayRecord {
+    pub date: NaiveDate,
+    pub shade: Shade,
+}
+
+impl HeatmapDayRecord {
+    /// One record per day of `weeks`, oldest first
+    pub fn from_weeks(weeks: &[HeatmapWeek]) -> Vec<Self> {
+        weeks
+            .iter()
+            .flat_map(|week| {
+                week.week
+                    .days()
+                    .zip(week.days.iter())
+                    .map(|(date, shade)| HeatmapDayRecord {
+                        date,
+                        shade: *shade,
+                    })
+            })
+            .collect()
+    }
+}
+
+/// A backup to roll back to, as written by `habyt restore --format`
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct BackupRecord {
+    pub backup: String,
+}
+
+/// What an import did, as written by `habyt import --format`: a record of what each file
+/// or directory brought in, followed by one for each row or field it has something to say about
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct ImportRecord {
+    /// The file or directory imported from
+    pub source: String,
+    pub outcome: ImportOutcome,
+    /// Only for `ImportOutcome::Imported`
+    pub habits: Option<usize>,
+    pub logs: Option<usize>,
+    /// The line of a CSV file the record is about
+    pub line: Option<u64>,
+    /// The habit the record is about, where there's no line to tell it by
+    pub habit: Option<String>,
+    pub reason: Option<String>,
+}
+
+/// What an import did with a file, or with a row or field of it
+#[derive(Serialize, PartialEq, Eq, Debug, Copy, Clone)]
+pub enum ImportOutcome {
+    /// The habits and log entries brought in
+    Imported,
+    /// Skipped for being there already
+    Duplicate,
+    /// Skipped for failing validation
+    Rejected,
+    /// Imported with something left out or changed on the way
+    Unmapped,
+}
+
+impl ImportRecord {
+    pub fn imported(source: &str, habits: usize, logs: usize) -> Self {
+        ImportRecord {
+            source: source.into(),
+            outcome: ImportOutcome::Imported,
+            habits: Some(habits),
+            logs: Some(logs),
+            line: None,
+            habit: None,
+            reason: None,
+        }
+    }
+
+    /// The records of importing the CSV file `source`, one per row reported on
+    pub fn from_csv(source: &str, import: &CsvImport) -> Vec<Self> {
+        let issues = [
+            (ImportOutcome::Duplicate, &import.duplicates),
+            (ImportOutcome::Rejected, &import.rejected),
+            (ImportOutcome::Unmapped, &import.unmapped),
+        ];
+        let mut records = vec![ImportRecord::imported(source, import.habits, import.logs)];
+        for (outcome, issues) in issues {
+            records.extend(issues.iter().map(|issue| ImportRecord {
+                source: source.into(),
+                outcome,
+                habits: None,
+                logs: None,
+                line: Some(issue.line),
+                habit: None,
+                reason: Some(issue.reason.clone()),
+            }));
+        }
+        records
+    }
+
+    /// The records of importing the Loop Habit Tracker export in `source`, one per note
+    pub fn from_loop(source: &str, import: &LoopImport) -> Vec<Self> {
+        let notes = [
+            (ImportOutcome::Unmapped, &import.unmapped),
+            (ImportOutcome::Rejected, &import.rejected),
+        ];
+        let mut records = vec![ImportRecord::imported(source, import.habits, import.logs)];
+        for (outcome, notes) in notes {
+            records.extend(notes.iter().map(|note| ImportRecord {
+                source: source.into(),
+                outcome,
+                habits: None,
+                logs: None,
+                line: None,
+                habit: Some(note.habit.clone()),
+                reason: Some(note.note.clone()),
+            }));
+        }
+        records
+    }
+}
+
+/// What an export wrote, as written by `habyt export --format`
+#[derive(Serialize, PartialEq, Debug, Clone)]
+pub struct ExportRecord {
+    /// The directory or file written to
+    pub path: String,
+    pub habits: usize,
+    /// Log entries, or for an iCalendar file the days completed
+    pub logs: usize,
+}
+
+#[cfg(test)]
+mod output_tests {
+    use crate::csv_io::{CsvImport, CsvRowIssue};
+    use crate::models::{Habit, HabitLog, HabitName, HabitUnit, Schedule, Streak};
+    use crate::output::{
+        write_records, HabitRecord, ImportOutcome, ImportRecord, LogRecord, OutputFormat,
+    };
+    use crate::test_support::day;
+    use chrono::Weekday;
+
+    fn running() -> Habit {
+        let mut habit = Habit::new(
+            1,
+            HabitName("running".into()),
+            5.,
+            HabitUnit("km".into()),
+            Some(Streak::Daily),
+            None,
+            None,
+            None,
+        );
+        habit.schedule = Some(Schedule::Weekdays(vec![Weekday::Mon, Weekday::Fri]));
+        habit
+    }
+
+    fn log_record() -> LogRecord {
+        let log_entry = HabitLog::new_dated(1, 6., Some("windy".into()), day("2021-06-07"));
+        LogRecord::new(&log_entry, Some(&running().name))
+    }
+
+    fn written<T: serde::Serialize>(records: &[T], format: OutputFormat) -> String {
+        let mut out = vec![];
+        write_records(records, format, &mut out).expect("Failed to write the records");
+        String::from_utf8(out).expect("Failed to read the records back")
+    }
+
+    #[test]
+    fn json_records_have_a_field_for_everything() {
+        // arrange
+        let habits = [HabitRecord::new(&running(), day("2021-06-07"))];
+
+        // act
+        let json = written(&habits, OutputFormat::Json);
+
+        // assert
+        assert!(json.starts_with("[\n"));
+        assert!(json.contains("\"name\": \"running\""));
+        assert!(json.contains("\"schedule\": \"mon,fri\""));
+        assert!(json.contains("\"current_streak\": null"));
+    }
+
+    #[test]
+    fn yaml_records_keep_the_field_names() {
+        // act
+        let yaml = written(&[log_record()], OutputFormat::Yaml);
+
+        // assert
+        assert!(yaml.contains("habit_name: running"));
+        assert!(yaml.contains("date: 2021-06-07"));
+    }
+
+    #[test]
+    fn csv_records_are_rows_under_a_header() {
+        // act
+        let csv = written(&[log_record()], OutputFormat::Csv);
+
+        // assert
+        assert_eq!(
+            csv,
+            "id,habit_id,habit_name,quantum,notes,date\n0,1,running,6.0,windy,2021-06-07\n"
+        );
+    }
+
+    #[test]
+    fn no_records_are_an_empty_list_or_nothing_at_all_in_csv() {
+        // arrange
+        let records: [LogRecord; 0] = [];
+
+        // act
+        let json = written(&records, OutputFormat::Json);
+        let yaml = written(&records, OutputFormat::Yaml);
+        let csv = written(&records, OutputFormat::Csv);
+
+        // assert
+        assert_eq!(json, "[]\n");
+        assert_eq!(yaml, "---\n[]\n");
+        assert_eq!(csv, "");
+    }
+
+    #[test]
+    fn records_are_not_written_as_a_table() {
+        let written = write_records(&[log_record()], OutputFormat::Table, vec![]);
+        assert!(written.is_err());
+    }
+
+    #[test]
+    fn output_formats_are_read_by_name() {
+        assert_eq!("yaml".parse::<OutputFormat>().unwrap(), OutputFormat::Yaml);
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn an_import_has_a_record_per_row_reported_on() {
+        // arrange
+        let issue = |line: u64, reason: &str| CsvRowIssue {
+            line,
+            reason: reason.into(),
+        };
+        let import = CsvImport {
+            habits: 0,
+            logs: 1,
+            duplicates: vec![issue(2, "already logged")],
+            rejected: vec![issue(4, "no such habit")],
+            unmapped: vec![],
+        };
+
+        // act
+        let records = ImportRecord::from_csv("logs.csv", &import);
+
+        // assert
+        let outcomes: Vec<_> = records.iter().map(|record| record.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ImportOutcome::Imported,
+                ImportOutcome::Duplicate,
+                ImportOutcome::Rejected
+            ]
+        );
+        assert_eq!(records[0].logs, Some(1));
+        assert_eq!(records[2].line, Some(4));
+        assert_eq!(records[2].reason.as_deref(), Some("no such habit"));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::models::{Habit, HabitLog, HasPosNeg, Schedule};
+use crate::streak::{quantum_by_day, quantum_by_period, Period};
+use chrono::NaiveDate;
+use serde::Serialize;
+
+/// Where a habit stands in its current period
+#[derive(Serialize, PartialEq, Eq, Debug, Copy, Clone)]
+pub enum ProgressStatus {
+    /// The goal is met
+    Done,
+    /// The goal is not met yet
+    Pending,
+    /// A negative habit still within its limit
+    UnderLimit,
+    /// A negative habit past its limit
+    OverLimit,
+}
+
+/// How far along a habit is, as of some day
+#[derive(PartialEq, Debug, Clone)]
+pub struct Progress {
+    /// The period the quantum is counted in, just the day for habits done a few times per period
+    pub period: Period,
+    pub logged: f64,
+    /// What's left to reach the goal, or to stay within the limit of a negative habit
+    pub remaining: f64,
+    pub status: ProgressStatus,
+    /// Days met so far and days needed, for habits done a number of times per period
+    pub times: Option<(u32, u32)>,
+}
+
+/// Whether `habit` is waiting to be done on `today`: active, not paused, and due by its schedule
+pub fn is_due_today(habit: &Habit, today: NaiveDate) -> bool {
+    habit.active && !habit.is_paused_on(today) && habit.is_due_on(today)
+}
+
+/// Work out how far along `habit` is on `today`, from its logs.
+///
+/// A habit done a number of times per period is counted per day, and is done once either
+/// today's quantum or the number of times is reached.
+pub fn progress(habit: &Habit, logs: &[&HabitLog], today: NaiveDate) -> Progress {
+    let streak_period = Period::containing(habit.streak, today);
+    let (period, logged, times) = match habit.schedule {
+        Some(Schedule::TimesPer(needed)) => {
+            let by_day = quantum_by_day(habit, logs, today);
+            let days_met = streak_period
+                .days()
+                .filter(|day| *day <= today)
+                .filter(|day| habit.is_met(by_day.get(day).copied().unwrap_or(0.)))
+                .count() as u32;
+            (
+                Period {
+                    start: today,
+                    end: today,
+                },
+                by_day.get(&today).copied().unwrap_or(0.),
+                Some((days_met, needed)),
+            )
+        }
+        _ => (
+            streak_period,
+            quantum_by_period(habit, logs, today)
+                .get(&streak_period)
+                .copied()
+                .unwrap_or(0.),
+            None,
+        ),
+    };
+
+    let met = habit.is_met(logged) || times.is_some_and(|(days_met, needed)| days_met >= needed);
+    let status = match (habit.is_negative(), met) {
+        (true, true) => ProgressStatus::UnderLimit,
+        (true, false) => ProgressStatus::OverLimit,
+        (false, true) => ProgressStatus::Done,
+        (false, false) => ProgressStatus::Pending,
+    };
+    Progress {
+        period,
+        logged,
+        remaining: if habit.is_negative() {
+            habit.quantum - logged
+        } else {
+            (habit.quantum - logged).max(0.)
+        },
+        status,
+        times,
+    }
+}
+
+#[cfg(test)]
+mod progress_tests {
+    use crate::models::{HabitLog, Kind, Schedule, Streak};
+    use crate::progress::{progress, ProgressStatus};
+    use crate::test_support::{day, habit, log};
+
+    #[test]
+    fn progress_counts_what_was_logged_in_the_current_period() {
+        // arrange
+        let weekly = habit(Streak::Weekly, 1000., Kind::Positive);
+        let limit = habit(Streak::Daily, 5., Kind::Negative);
+        let logs = [log("2021-06-07", 400.), log("2021-06-09", 7.)];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let weekly = progress(&weekly, &logs, day("2021-06-09"));
+        let limit = progress(&limit, &logs, day("2021-06-09"));
+
+        // assert
+        assert_eq!(weekly.logged, 407.);
+        assert_eq!(weekly.remaining, 593.);
+        assert_eq!(limit.logged, 7.);
+        assert_eq!(weekly.status, ProgressStatus::Pending);
+        assert_eq!(limit.status, ProgressStatus::OverLimit);
+        assert_eq!(limit.remaining, -2.);
+    }
+
+    #[test]
+    fn times_per_week_habits_are_done_once_enough_days_are_met() {
+        // arrange
+        let mut habit = habit(Streak::Weekly, 5., Kind::Positive);
+        habit.schedule = Some(Schedule::TimesPer(2));
+        let logs = [log("2021-06-07", 5.), log("2021-06-08", 5.)];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let progress = progress(&habit, &logs, day("2021-06-09"));
+
+        // assert
+        assert_eq!(progress.logged, 0.);
+        assert_eq!(progress.times, Some((2, 2)));
+        assert_eq!(progress.status, ProgressStatus::Done);
+    }
+}
@@ -0,0 +1,240 @@
+use crate::models::{Habit, HabitLog, HasPosNeg, Streak};
+use crate::streak::{period_outcomes, quantum_by_day, quantum_by_period, Period};
+use chrono::NaiveDate;
+use serde::Serialize;
+
+/// Which way the quantum logged per period is heading
+#[derive(Serialize, PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Trend {
+    Rising,
+    Steady,
+    Falling,
+}
+
+/// How a habit went over a range of days
+#[derive(PartialEq, Debug, Clone)]
+pub struct HabitStats {
+    /// The days looked at, from the later of the range start and when tracking began
+    pub range: Period,
+    /// Periods of the habit's `Streak` the habit was due in
+    pub periods: u32,
+    /// Those of the `periods` the habit was met in
+    pub completed: u32,
+    /// `completed` over `periods`, between 0 and 1
+    pub completion_rate: f64,
+    pub total_quantum: f64,
+    /// The quantum logged in the `periods` over their number, leaving out paused periods
+    pub average_per_period: f64,
+    /// The ISO week with the most quantum logged, or the least for a negative habit
+    pub best_week: Option<(Period, f64)>,
+    /// The ISO week with the least quantum logged, or the most for a negative habit
+    pub worst_week: Option<(Period, f64)>,
+    /// The longest run of days without a single log
+    pub longest_gap: Option<Period>,
+    /// The quantum per period in the second half of the range against the first half
+    pub trend: Trend,
+}
+
+/// Quantum per period needs to move by more than this share to count as a trend
+const TREND_THRESHOLD: f64 = 0.1;
+
+/// Work out the statistics of `habit` from `from` to `to`, both included, from its logs
+pub fn habit_stats(
+    habit: &Habit,
+    logs: &[&HabitLog],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> HabitStats {
+    // Earlier logs still tell when tracking began, and count towards a period overlapping `from`
+    let outcomes: Vec<(Period, bool)> = period_outcomes(habit, logs, to)
+        .into_iter()
+        .filter(|(period, _)| period.end >= from)
+        .collect();
+    let logs: Vec<&HabitLog> = logs
+        .iter()
+        .copied()
+        .filter(|log_entry| log_entry.habit_id == habit.id && log_entry.date >= from)
+        .collect();
+    let tracked_from = outcomes
+        .first()
+        .map(|(period, _)| period.start.max(from))
+        .unwrap_or(from);
+    let range = Period {
+        start: tracked_from,
+        end: to.max(tracked_from),
+    };
+
+    let periods = outcomes.len() as u32;
+    let completed = outcomes.iter().filter(|(_, met)| *met).count() as u32;
+    let by_period = quantum_by_period(habit, &logs, to);
+    let total_quantum = by_period
+        .values()
+        .fold(0., |total, quantum| total + quantum);
+
+    let mut ranked = totals_by_week(habit, &logs, range);
+    ranked.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+    if habit.is_negative() {
+        ranked.reverse();
+    }
+
+    let quantum_per_period: Vec<f64> = outcomes
+        .iter()
+        .map(|(period, _)| by_period.get(period).copied().unwrap_or(0.))
+        .collect();
+
+    HabitStats {
+        range,
+        periods,
+        completed,
+        completion_rate: if periods == 0 {
+            0.
+        } else {
+            completed as f64 / periods as f64
+        },
+        total_quantum,
+        average_per_period: if periods == 0 {
+            0.
+        } else {
+            quantum_per_period.iter().sum::<f64>() / periods as f64
+        },
+        best_week: ranked.last().copied(),
+        worst_week: ranked.first().copied(),
+        longest_gap: longest_gap(habit, &logs, range),
+        trend: trend(&quantum_per_period),
+    }
+}
+
+/// The quantum logged in each ISO week overlapping `range`, oldest first
+fn totals_by_week(habit: &Habit, logs: &[&HabitLog], range: Period) -> Vec<(Period, f64)> {
+    let by_day = quantum_by_day(habit, logs, range.end);
+    let mut weeks = vec![];
+    let mut week = Period::containing(Streak::Weekly, range.start);
+    while week.start <= range.end {
+        let total = by_day
+            .range(week.start..=week.end)
+            .fold(0., |total, (_, quantum)| total + quantum);
+        weeks.push((week, total));
+        week = week.next(Streak::Weekly);
+    }
+    weeks
+}
+
+fn longest_gap(habit: &Habit, logs: &[&HabitLog], range: Period) -> Option<Period> {
+    let by_day = quantum_by_day(habit, logs, range.end);
+    let mut longest: Option<Period> = None;
+    let mut gap_start = None;
+    for day in range.days().chain(std::iter::once(range.end.succ())) {
+        let logged = day > range.end || by_day.contains_key(&day);
+        match (logged, gap_start) {
+            (false, None) => gap_start = Some(day),
+            (true, Some(start)) => {
+                let gap = Period {
+                    start,
+                    end: day.pred(),
+                };
+                if longest.is_none_or(|longest| gap.end - gap.start > longest.end - longest.start) {
+                    longest = Some(gap);
+                }
+                gap_start = None;
+            }
+            _ => {}
+        }
+    }
+    longest
+}
+
+fn trend(quantum_per_period: &[f64]) -> Trend {
+    if quantum_per_period.len() < 2 {
+        return Trend::Steady;
+    }
+    let (first, second) = quantum_per_period.split_at(quantum_per_period.len() / 2);
+    let average = |half: &[f64]| half.iter().sum::<f64>() / half.len() as f64;
+    let (first, second) = (average(first), average(second));
+    if second > first * (1. + TREND_THRESHOLD) {
+        Trend::Rising
+    } else if second < first * (1. - TREND_THRESHOLD) {
+        Trend::Falling
+    } else {
+        Trend::Steady
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use crate::models::{HabitLog, Kind, Streak};
+    use crate::stats::{habit_stats, Trend};
+    use crate::test_support::{day, habit, log};
+
+    #[test]
+    fn stats_sum_up_the_range() {
+        // arrange
+        let habit = habit(Streak::Daily, 500., Kind::Positive);
+        // Two weeks, starting on a Monday, with a gap in the middle
+        let logs = [
+            log("2021-06-07", 500.),
+            log("2021-06-08", 100.),
+            log("2021-06-09", 500.),
+            log("2021-06-14", 800.),
+            log("2021-06-15", 900.),
+            log("2021-06-16", 700.),
+            log("2021-06-17", 600.),
+            log("2021-06-18", 900.),
+        ];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let stats = habit_stats(&habit, &logs, day("2021-06-07"), day("2021-06-18"));
+
+        // assert
+        assert_eq!(stats.periods, 12);
+        assert_eq!(stats.completed, 7);
+        assert_eq!(stats.total_quantum, 5000.);
+        assert_eq!(stats.best_week.unwrap().0.start, day("2021-06-14"));
+        assert_eq!(stats.worst_week.unwrap().1, 1100.);
+        let gap = stats.longest_gap.expect("There was a gap.");
+        assert_eq!((gap.start, gap.end), (day("2021-06-10"), day("2021-06-13")));
+        assert_eq!(stats.trend, Trend::Rising);
+    }
+
+    #[test]
+    fn logs_of_paused_periods_are_left_out_of_the_average() {
+        // arrange
+        let mut habit = habit(Streak::Daily, 500., Kind::Positive);
+        habit
+            .pause(day("2021-06-09"), Some(day("2021-06-10")))
+            .expect("Failed to pause habit.");
+        let logs = [
+            log("2021-06-07", 500.),
+            log("2021-06-08", 500.),
+            log("2021-06-09", 100.),
+            log("2021-06-11", 500.),
+        ];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let stats = habit_stats(&habit, &logs, day("2021-06-07"), day("2021-06-11"));
+
+        // assert
+        assert_eq!(stats.periods, 3);
+        assert_eq!(stats.total_quantum, 1600.);
+        assert_eq!(stats.average_per_period, 500.);
+    }
+
+    #[test]
+    fn stats_of_a_quantum_that_is_not_a_number_do_not_panic() {
+        // arrange
+        let habit = habit(Streak::Daily, 5., Kind::Positive);
+        // Stores written before quantums were validated may still hold a NaN
+        let mut not_a_number = log("2021-06-16", 1.);
+        not_a_number.quantum = f64::NAN;
+        let logs = [log("2021-06-07", 3.), not_a_number];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let stats = habit_stats(&habit, &logs, day("2021-06-04"), day("2021-06-18"));
+
+        // assert
+        assert_eq!(stats.worst_week.unwrap().0.start, day("2021-06-07"));
+        assert_eq!(stats.worst_week.unwrap().1, 3.);
+    }
+}
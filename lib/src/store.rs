@@ -1,18 +1,39 @@
-use crate::models::{DeletedHabit, Habit, HabitDraft, HabitId, HabitLog, HabitPatch};
-use chrono::NaiveDate;
+use crate::dates::validate_log_date;
+use crate::error::{HabytError, HabytResult};
+use crate::models::validate_quantum;
+use crate::models::{
+    DeletePolicy, DeletedHabit, Habit, HabitDraft, HabitId, HabitLog, HabitLogPatch, HabitName,
+    HabitPatch, Tombstone,
+};
+use chrono::{Local, NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct HabitStore {
     current_id: u32,
     data: HashMap<HabitId, Habit>,
+    /// Habits deleted along with their logs, which moved to `HabitLogStore::archive`
+    #[serde(default)]
+    archive: HashMap<HabitId, Habit>,
+    /// Habits deleted while their logs were kept
+    #[serde(default)]
+    tombstones: HashMap<HabitId, Tombstone>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct HabitLogStore {
     current_id: u32,
     data: HashMap<u32, HabitLog>,
+    /// Logs of archived habits
+    #[serde(default)]
+    archive: HashMap<u32, HabitLog>,
+    /// Log entries added or changed since the store was loaded, for backends saving only those
+    #[serde(skip)]
+    changed: HashSet<u32>,
+    /// Log entries deleted or archived since the store was loaded
+    #[serde(skip)]
+    removed: HashSet<u32>,
 }
 
 impl HabitStore {
@@ -20,13 +41,14 @@ impl HabitStore {
         Self {
             current_id: 0,
             data: HashMap::new(),
+            archive: HashMap::new(),
+            tombstones: HashMap::new(),
         }
     }
 
-    pub fn create(&mut self, draft: HabitDraft) -> HabitId {
-        let id = self.generate_id();
-        let habit = Habit::new(
-            id,
+    pub fn create(&mut self, draft: HabitDraft) -> HabytResult<HabitId> {
+        let mut habit = Habit::try_new(
+            self.current_id + 1,
             draft.name,
             draft.quantum,
             draft.unit,
@@ -34,17 +56,105 @@ impl HabitStore {
             draft.difficulty,
             draft.kind,
             draft.notes,
-        );
+        )?;
+        habit.schedule = draft.schedule;
+        let now = Local::now().naive_local();
+        habit.created_at = Some(now);
+        habit.updated_at = Some(now);
+        let id = self.generate_id();
         self.data.insert(habit.id, habit);
-        id
+        Ok(id)
     }
 
     pub fn delete(&mut self, id: HabitId) -> Option<DeletedHabit> {
-        self.data.remove(&id).map(DeletedHabit)
+        self.data.remove(&id).map(|mut habit| {
+            habit.archived_at = Some(Local::now().naive_local());
+            DeletedHabit(habit)
+        })
+    }
+
+    /// Delete a habit, dealing with its logs in `store_log` as the `policy` says
+    pub fn delete_with(
+        &mut self,
+        id: HabitId,
+        policy: DeletePolicy,
+        store_log: &mut HabitLogStore,
+    ) -> Option<DeletedHabit> {
+        let deleted = self.delete(id)?;
+        let habit = &deleted.0;
+        match policy {
+            DeletePolicy::Cascade => {
+                let cascaded: Vec<u32> = store_log
+                    .list_by_habit(id)
+                    .iter()
+                    .map(|log_entry| log_entry.id)
+                    .collect();
+                for log_id in cascaded {
+                    store_log.delete(log_id);
+                }
+            }
+            DeletePolicy::Tombstone => self.bury(
+                id,
+                Some(habit.name.clone()),
+                habit
+                    .archived_at
+                    .unwrap_or_else(|| Local::now().naive_local()),
+            ),
+            DeletePolicy::Archive => {
+                self.archive.insert(id, habit.clone());
+                store_log.archive_habit(id);
+            }
+        }
+        Some(deleted)
+    }
+
+    /// Leave a tombstone for a habit that's gone, for its logs to point at
+    pub(crate) fn bury(&mut self, id: HabitId, name: Option<HabitName>, deleted_at: NaiveDateTime) {
+        self.tombstones.insert(
+            id,
+            Tombstone {
+                id,
+                name,
+                deleted_at,
+            },
+        );
+    }
+
+    pub fn tombstone(&self, id: HabitId) -> Option<&Tombstone> {
+        self.tombstones.get(&id)
+    }
+
+    /// The name of a habit, or of the tombstone it left behind
+    pub fn name_of(&self, id: HabitId) -> Option<&HabitName> {
+        match self.get(id) {
+            Some(habit) => Some(&habit.name),
+            None => self
+                .tombstone(id)
+                .and_then(|tombstone| tombstone.name.as_ref()),
+        }
+    }
+
+    pub fn archived(&self, id: HabitId) -> Option<&Habit> {
+        self.archive.get(&id)
+    }
+
+    pub fn list_archived(&self) -> Vec<&Habit> {
+        self.archive.values().collect()
+    }
+
+    /// Bring an archived habit back, along with its logs from `store_log`
+    pub fn restore(&mut self, id: HabitId, store_log: &mut HabitLogStore) -> HabytResult<&Habit> {
+        let mut habit = self.archive.remove(&id).ok_or_else(|| {
+            HabytError::NotFound(format!("There's no archived habit with id {}", id))
+        })?;
+        habit.archived_at = None;
+        habit.updated_at = Some(Local::now().naive_local());
+        store_log.unarchive_habit(id);
+        Ok(self.data.entry(id).or_insert(habit))
     }
 
     pub fn list(&self) -> Vec<&Habit> {
-        self.data.iter().map(|(_, habit)| habit).collect()
+        self.data.values().collect()
     }
 
     fn generate_id(&mut self) -> HabitId {
@@ -56,34 +166,101 @@ impl HabitStore {
         self.data.get(&id)
     }
 
-    pub fn update(&mut self, id: HabitId, patch: HabitPatch) -> Option<()> {
-        self.data.get_mut(&id).map(|h| {
-            if let Some(name) = patch.name {
-                if !name.0.is_empty() {
-                    h.name = name;
-                }
-            }
-            if let Some(quantum) = patch.quantum {
-                if quantum > 0. {
-                    h.quantum = quantum;
-                }
-            }
-            if let Some(unit) = patch.unit {
-                h.unit = unit;
-            }
-            if let Some(streak) = patch.streak {
-                h.streak = streak;
-            }
-            if let Some(difficulty) = patch.difficulty {
-                h.difficulty = difficulty;
-            }
-            if let Some(kind) = patch.kind {
-                h.kind = kind;
+    pub(crate) fn current_id(&self) -> u32 {
+        self.current_id
+    }
+
+    /// Archived habits and tombstones, for backends that keep them apart from the habits
+    pub(crate) fn graveyard(&self) -> (Vec<&Habit>, Vec<&Tombstone>) {
+        (
+            self.archive.values().collect(),
+            self.tombstones.values().collect(),
+        )
+    }
+
+    /// Rebuild a store from habits persisted elsewhere, e.g. by `HabitStoreSqlite`
+    pub(crate) fn from_parts(
+        current_id: u32,
+        habits: Vec<Habit>,
+        archive: Vec<Habit>,
+        tombstones: Vec<Tombstone>,
+    ) -> Self {
+        Self {
+            current_id,
+            data: habits.into_iter().map(|habit| (habit.id, habit)).collect(),
+            archive: archive.into_iter().map(|habit| (habit.id, habit)).collect(),
+            tombstones: tombstones
+                .into_iter()
+                .map(|tombstone| (tombstone.id, tombstone))
+                .collect(),
+        }
+    }
+
+    /// Pause a habit, see `Habit::pause`
+    pub fn pause(
+        &mut self,
+        id: HabitId,
+        from: NaiveDate,
+        until: Option<NaiveDate>,
+    ) -> HabytResult<()> {
+        let habit = self.get_mut(id)?;
+        habit.pause(from, until)?;
+        habit.updated_at = Some(Local::now().naive_local());
+        Ok(())
+    }
+
+    /// Resume a paused habit, see `Habit::resume`
+    pub fn resume(&mut self, id: HabitId, today: NaiveDate) -> HabytResult<()> {
+        let habit = self.get_mut(id)?;
+        habit.resume(today)?;
+        habit.updated_at = Some(Local::now().naive_local());
+        Ok(())
+    }
+
+    pub(crate) fn get_mut(&mut self, id: HabitId) -> HabytResult<&mut Habit> {
+        self.data
+            .get_mut(&id)
+            .ok_or_else(|| HabytError::NotFound(format!("There's no habit with id {}", id)))
+    }
+
+    pub fn update(&mut self, id: HabitId, patch: HabitPatch) -> HabytResult<()> {
+        if patch.notes.as_ref().is_some_and(|notes| notes.len() > 280) {
+            return Err(HabytError::validation(&format!(
+                "Habit's note cannot be longer than {} characters!",
+                280
+            )));
+        }
+        let h = self.get_mut(id)?;
+        if let Some(name) = patch.name {
+            if !name.0.is_empty() {
+                h.name = name;
             }
-            if let Some(notes) = patch.notes {
-                h.notes = notes;
+        }
+        if let Some(quantum) = patch.quantum {
+            if quantum > 0. {
+                h.quantum = quantum;
             }
-        })
+        }
+        if let Some(unit) = patch.unit {
+            h.unit = unit;
+        }
+        if let Some(streak) = patch.streak {
+            h.streak = streak;
+        }
+        if let Some(difficulty) = patch.difficulty {
+            h.difficulty = difficulty;
+        }
+        if let Some(kind) = patch.kind {
+            h.kind = kind;
+        }
+        if let Some(notes) = patch.notes {
+            h.notes = notes;
+        }
+        if let Some(schedule) = patch.schedule {
+            h.schedule = schedule;
+        }
+        h.updated_at = Some(Local::now().naive_local());
+        Ok(())
     }
 }
 
@@ -92,13 +269,44 @@ impl HabitLogStore {
         Self {
             current_id: 0,
             data: HashMap::new(),
+            archive: HashMap::new(),
+            changed: HashSet::new(),
+            removed: HashSet::new(),
         }
     }
 
-    pub fn add(&mut self, log: HabitLog) -> u32 {
+    /// Add a log entry, giving it an id of its own.
+    ///
+    /// The habit it's logged against has to be in `habits` and not paused on the entry's date,
+    /// and unless `force`d the date has to be one it could have been done on by `today`, see
+    /// `validate_log_date`.
+    pub fn add(
+        &mut self,
+        mut log: HabitLog,
+        habits: &HabitStore,
+        today: NaiveDate,
+        force: bool,
+    ) -> HabytResult<u32> {
+        match habits.get(log.habit_id) {
+            None => {
+                return Err(HabytError::NotFound(format!(
+                    "There's no habit with id {}",
+                    log.habit_id
+                )))
+            }
+            Some(habit) if habit.is_paused_on(log.date) => {
+                return Err(HabytError::validation(&format!(
+                    "{} is paused on {}, resume it to log it",
+                    habit.name, log.date
+                )))
+            }
+            Some(habit) => validate_log_date(log.date, today, habit.tracked_since(), force)?,
+        }
         let id = self.generate_id();
+        log.id = id;
         self.data.insert(id, log);
-        id
+        self.changed.insert(id);
+        Ok(id)
     }
 
     fn generate_id(&mut self) -> u32 {
@@ -106,42 +314,186 @@ impl HabitLogStore {
         self.current_id
     }
 
+    /// Correct a log entry; a new date is checked like `add` does, unless `force`d
+    pub fn update(
+        &mut self,
+        id: u32,
+        patch: HabitLogPatch,
+        habits: &HabitStore,
+        today: NaiveDate,
+        force: bool,
+    ) -> HabytResult<()> {
+        if patch.notes.as_ref().is_some_and(|notes| notes.len() > 280) {
+            return Err(HabytError::validation(&format!(
+                "Log's note cannot be longer than {} characters!",
+                280
+            )));
+        }
+        let log_entry = self
+            .data
+            .get_mut(&id)
+            .ok_or_else(|| HabytError::NotFound(format!("There's no log entry with id {}", id)))?;
+        // Check everything before changing anything, so a refused patch leaves no trace
+        if let Some(quantum) = patch.quantum {
+            validate_quantum(quantum)?;
+        }
+        if let Some(date) = patch.date {
+            let tracked_since = habits
+                .get(log_entry.habit_id)
+                .and_then(|habit| habit.tracked_since());
+            validate_log_date(date, today, tracked_since, force)?;
+        }
+        if let Some(quantum) = patch.quantum {
+            log_entry.quantum = quantum;
+        }
+        if let Some(notes) = patch.notes {
+            log_entry.notes = notes;
+        }
+        if let Some(date) = patch.date {
+            log_entry.date = date;
+        }
+        self.changed.insert(id);
+        Ok(())
+    }
+
+    pub fn delete(&mut self, id: u32) -> Option<HabitLog> {
+        let deleted = self.data.remove(&id)?;
+        self.changed.remove(&id);
+        self.removed.insert(id);
+        Some(deleted)
+    }
+
+    /// Move all the logs of a habit to the archive
+    fn archive_habit(&mut self, habit_id: HabitId) {
+        for id in move_logs(&mut self.data, &mut self.archive, habit_id) {
+            self.changed.remove(&id);
+            self.removed.insert(id);
+        }
+    }
+
+    /// Move all the logs of a habit back out of the archive
+    fn unarchive_habit(&mut self, habit_id: HabitId) {
+        for id in move_logs(&mut self.archive, &mut self.data, habit_id) {
+            self.removed.remove(&id);
+            self.changed.insert(id);
+        }
+    }
+
+    pub fn list_archived(&self) -> Vec<&HabitLog> {
+        self.archive.values().collect()
+    }
+
     pub fn get(&self, id: u32) -> Option<&HabitLog> {
         self.data.get(&id)
     }
 
     pub fn list(&self) -> Vec<&HabitLog> {
-        self.data.iter().map(|(_, log_entry)| log_entry).collect()
+        self.data.values().collect()
+    }
+
+    pub fn list_by_habit(&self, habit_id: HabitId) -> Vec<&HabitLog> {
+        self.data
+            .values()
+            .filter(|log_entry| log_entry.habit_id == habit_id)
+            .collect()
+    }
+
+    pub(crate) fn current_id(&self) -> u32 {
+        self.current_id
+    }
+
+    /// Log entries along with the ids they are stored under
+    pub(crate) fn entries(&self) -> Vec<(u32, &HabitLog)> {
+        self.data
+            .iter()
+            .map(|(id, log_entry)| (*id, log_entry))
+            .collect()
+    }
+
+    /// Archived log entries along with the ids they are stored under
+    pub(crate) fn archived_entries(&self) -> Vec<(u32, &HabitLog)> {
+        self.archive
+            .iter()
+            .map(|(id, log_entry)| (*id, log_entry))
+            .collect()
+    }
+
+    /// The log entries added or changed, and the ids of those deleted or archived, since the
+    /// store was loaded
+    pub(crate) fn changes(&self) -> (Vec<(u32, &HabitLog)>, Vec<u32>) {
+        let changed = self
+            .changed
+            .iter()
+            .filter_map(|id| self.data.get(id).map(|log_entry| (*id, log_entry)))
+            .collect();
+        (changed, self.removed.iter().copied().collect())
+    }
+
+    /// Count every log entry as changed, e.g. to save a store loaded from another backend
+    pub(crate) fn touch_all(&mut self) {
+        self.changed = self.data.keys().copied().collect();
+    }
+
+    /// Rebuild a log store from entries persisted elsewhere, e.g. by `HabitStoreSqlite`
+    pub(crate) fn from_parts(
+        current_id: u32,
+        entries: Vec<(u32, HabitLog)>,
+        archive: Vec<(u32, HabitLog)>,
+    ) -> Self {
+        Self {
+            current_id,
+            data: entries.into_iter().collect(),
+            archive: archive.into_iter().collect(),
+            changed: HashSet::new(),
+            removed: HashSet::new(),
+        }
     }
 
-    pub fn get_log_by_habit(&self) -> HashMap<u32, Vec<(NaiveDate, f64)>> {
+    pub fn get_log_by_habit(&self) -> HashMap<HabitId, Vec<&HabitLog>> {
         let mut log = HashMap::new();
 
-        for (_, log_entry) in self.data.iter() {
-            if !log.contains_key(&log_entry.id) {
-                log.insert(log_entry.id, vec![]);
-            }
-            log.get_mut(&log_entry.id)
-                .unwrap()
-                .push((log_entry.date, log_entry.quantum));
+        for log_entry in self.data.values() {
+            log.entry(log_entry.habit_id)
+                .or_insert_with(Vec::new)
+                .push(log_entry);
         }
         log
     }
 
     fn get_log_entry(&self, habit_id: &HabitId) -> Option<(&u32, &HabitLog)> {
-        return self
-            .data
+        self.data
             .iter()
-            .find(|(_, log_entry)| log_entry.id == *habit_id);
+            .find(|(_, log_entry)| log_entry.habit_id == *habit_id)
+    }
+}
+
+/// Move the logs of a habit from one map to the other, returning their ids
+fn move_logs(
+    from: &mut HashMap<u32, HabitLog>,
+    to: &mut HashMap<u32, HabitLog>,
+    habit_id: HabitId,
+) -> Vec<u32> {
+    let moved: Vec<u32> = from
+        .iter()
+        .filter(|(_, log_entry)| log_entry.habit_id == habit_id)
+        .map(|(id, _)| *id)
+        .collect();
+    for id in moved.iter() {
+        if let Some(log_entry) = from.remove(id) {
+            to.insert(*id, log_entry);
+        }
     }
+    moved
 }
 
 #[cfg(test)]
 mod habit_store_tests {
     use crate::models::{
-        DeletedHabit, Habit, HabitDraft, HabitId, HabitName, HabitPatch, HabitUnit,
+        DeletePolicy, Habit, HabitDraft, HabitId, HabitLog, HabitLogPatch, HabitPatch,
     };
-    use crate::store::HabitStore;
+    use crate::store::{HabitLogStore, HabitStore};
+    use crate::test_support::day;
+    use chrono::Local;
     use fake::{Fake, Faker};
 
     #[test]
@@ -151,7 +503,9 @@ mod habit_store_tests {
         let mut habit_store = HabitStore::new();
 
         //act
-        let habit_id = habit_store.create(draft.clone());
+        let habit_id = habit_store
+            .create(draft.clone())
+            .expect("Failed to create habit.");
 
         //assert
         let habit = habit_store
@@ -167,7 +521,9 @@ mod habit_store_tests {
         //arrange
         let draft = HabitDraft::new((3..25).fake::<String>(), 700.0, (3..15).fake::<String>());
         let mut habit_store = HabitStore::new();
-        let habit_id = habit_store.create(draft.clone());
+        let habit_id = habit_store
+            .create(draft.clone())
+            .expect("Failed to create habit.");
 
         //act
         let deleted_habit = habit_store
@@ -179,6 +535,235 @@ mod habit_store_tests {
         assert_eq!(habit_store.get(habit_id), None);
     }
 
+    #[test]
+    fn creating_and_updating_a_habit_records_when_it_happened() {
+        //arrange
+        let draft = HabitDraft::new((3..25).fake::<String>(), 700.0, (3..15).fake::<String>());
+        let mut habit_store = HabitStore::new();
+        let habit_id = habit_store
+            .create(draft.clone())
+            .expect("Failed to create habit.");
+        let created = habit_store.get(habit_id).unwrap().clone();
+
+        //act
+        habit_store
+            .update(
+                habit_id,
+                HabitPatch::from("".into(), 650.0, "".into(), None, None, None, None),
+            )
+            .expect("Failed to update habit.");
+
+        //assert
+        let updated = habit_store.get(habit_id).unwrap();
+        assert!(created.created_at.is_some());
+        assert_eq!(updated.created_at, created.created_at);
+        assert!(updated.updated_at >= created.updated_at);
+        assert_eq!(updated.archived_at, None);
+    }
+
+    #[test]
+    fn habits_saved_without_timestamps_can_still_be_loaded() {
+        //arrange
+        let yaml = "---
+current_id: 1
+data:
+  1:
+    id: 1
+    name: writing
+    quantum: 750.0
+    unit: words
+    notes: \"\"
+    streak: Daily
+    difficulty: Easy
+    kind: Positive
+    active: true
+";
+
+        //act
+        let habit_store: HabitStore = serde_yaml::from_str(yaml).expect("Failed to parse store.");
+
+        //assert
+        assert_eq!(habit_store.get(1).unwrap().created_at, None);
+    }
+
+    #[test]
+    fn logging_in_the_future_or_before_tracking_should_fail_unless_forced() {
+        //arrange
+        let mut habit_store = HabitStore::new();
+        let mut habit_log_store = HabitLogStore::new();
+        let habit_id = create_habit_in_store(&mut habit_store).id;
+        habit_store.data.get_mut(&habit_id).unwrap().created_at =
+            Some(day("2021-06-01").and_hms(7, 0, 0));
+        let today = day("2021-06-10");
+        let on = |date: &str| HabitLog::new_dated(habit_id, 1.0, None, day(date));
+
+        //act
+        let future = habit_log_store.add(on("2021-06-11"), &habit_store, today, false);
+        let too_early = habit_log_store.add(on("2021-05-31"), &habit_store, today, false);
+        let forced = habit_log_store.add(on("2021-05-31"), &habit_store, today, true);
+        let log_id = habit_log_store
+            .add(on("2021-06-05"), &habit_store, today, false)
+            .expect("Failed to log habit.");
+        let moved = habit_log_store.update(
+            log_id,
+            HabitLogPatch::new(None, None, Some(day("2021-06-11"))),
+            &habit_store,
+            today,
+            false,
+        );
+
+        //assert
+        assert!(future.is_err());
+        assert!(too_early.is_err());
+        assert!(forced.is_ok());
+        assert!(moved.is_err());
+        assert_eq!(habit_log_store.get(log_id).unwrap().date, day("2021-06-05"));
+    }
+
+    #[test]
+    fn logging_against_a_missing_or_paused_habit_should_fail() {
+        //arrange
+        let mut habit_store = HabitStore::new();
+        let mut habit_log_store = HabitLogStore::new();
+        let paused_id = create_habit_in_store(&mut habit_store).id;
+        habit_store
+            .pause(paused_id, Local::today().naive_local(), None)
+            .expect("Failed to pause habit.");
+
+        //act
+        let missing = habit_log_store.add(
+            HabitLog::new(paused_id + 1, 1.0, None),
+            &habit_store,
+            Local::today().naive_local(),
+            false,
+        );
+        let paused = habit_log_store.add(
+            HabitLog::new(paused_id, 1.0, None),
+            &habit_store,
+            Local::today().naive_local(),
+            false,
+        );
+
+        //assert
+        assert!(missing.is_err());
+        assert!(paused.is_err());
+        assert!(habit_log_store.list().is_empty());
+    }
+
+    #[test]
+    fn days_before_an_open_ended_pause_can_still_be_logged() {
+        //arrange
+        let mut habit_store = HabitStore::new();
+        let mut habit_log_store = HabitLogStore::new();
+        let habit_id = create_habit_in_store(&mut habit_store).id;
+        habit_store.data.get_mut(&habit_id).unwrap().created_at =
+            Some(day("2021-06-01").and_hms(7, 0, 0));
+        habit_store
+            .pause(habit_id, day("2021-06-08"), None)
+            .expect("Failed to pause habit.");
+        let today = day("2021-06-10");
+        let on = |date: &str| HabitLog::new_dated(habit_id, 1.0, None, day(date));
+
+        //act
+        let backdated = habit_log_store.add(on("2021-06-07"), &habit_store, today, false);
+        let paused = habit_log_store.add(on("2021-06-08"), &habit_store, today, false);
+
+        //assert
+        assert!(backdated.is_ok());
+        assert!(paused.is_err());
+        assert_eq!(habit_log_store.list().len(), 1);
+    }
+
+    #[test]
+    fn log_entries_can_be_corrected_and_removed() {
+        //arrange
+        let mut habit_store = HabitStore::new();
+        let mut habit_log_store = HabitLogStore::new();
+        let habit_id = create_habit_in_store(&mut habit_store).id;
+        let log_id = habit_log_store
+            .add(
+                HabitLog::new(habit_id, 7500.0, None),
+                &habit_store,
+                Local::today().naive_local(),
+                false,
+            )
+            .expect("Failed to log habit.");
+
+        //act
+        habit_log_store
+            .update(
+                log_id,
+                HabitLogPatch::new(Some(750.0), Some("typo".into()), None),
+                &habit_store,
+                Local::today().naive_local(),
+                false,
+            )
+            .expect("Failed to update log entry.");
+        let updated = habit_log_store.get(log_id).cloned();
+        let deleted = habit_log_store.delete(log_id);
+
+        //assert
+        let updated = updated.expect("Log entry went missing.");
+        assert_eq!(updated.quantum, 750.0);
+        assert_eq!(updated.notes, "typo");
+        assert_eq!(deleted, Some(updated));
+        assert_eq!(habit_log_store.get(log_id), None);
+    }
+
+    #[test]
+    fn deleting_a_habit_deals_with_its_logs_as_asked() {
+        //arrange
+        let mut habit_store = HabitStore::new();
+        let mut habit_log_store = HabitLogStore::new();
+        let cascaded = create_logged_habit(&mut habit_store, &mut habit_log_store);
+        let tombstoned = create_logged_habit(&mut habit_store, &mut habit_log_store);
+        let archived = create_logged_habit(&mut habit_store, &mut habit_log_store);
+
+        //act
+        for (habit_id, policy) in [
+            (cascaded.0, DeletePolicy::Cascade),
+            (tombstoned.0, DeletePolicy::Tombstone),
+            (archived.0, DeletePolicy::Archive),
+        ]
+        .iter()
+        {
+            habit_store
+                .delete_with(*habit_id, *policy, &mut habit_log_store)
+                .expect("There was no habit to delete.");
+        }
+
+        //assert
+        assert!(habit_store.list().is_empty());
+        assert_eq!(habit_log_store.get(cascaded.1), None);
+        assert!(habit_log_store.get(tombstoned.1).is_some());
+        assert!(habit_store.tombstone(tombstoned.0).is_some());
+        assert_eq!(habit_log_store.get(archived.1), None);
+        assert_eq!(habit_log_store.archived_entries().len(), 1);
+    }
+
+    #[test]
+    fn restoring_an_archived_habit_brings_back_its_logs() {
+        //arrange
+        let mut habit_store = HabitStore::new();
+        let mut habit_log_store = HabitLogStore::new();
+        let (habit_id, log_id) = create_logged_habit(&mut habit_store, &mut habit_log_store);
+        habit_store
+            .delete_with(habit_id, DeletePolicy::Archive, &mut habit_log_store)
+            .expect("There was no habit to delete.");
+
+        //act
+        let restored = habit_store
+            .restore(habit_id, &mut habit_log_store)
+            .expect("Failed to restore habit.")
+            .clone();
+
+        //assert
+        assert_eq!(restored.archived_at, None);
+        assert!(habit_store.list_archived().is_empty());
+        assert_eq!(habit_log_store.get(log_id).unwrap().habit_id, habit_id);
+        assert!(habit_store.restore(habit_id, &mut habit_log_store).is_err());
+    }
+
     #[test]
     fn deleting_a_habit_that_does_not_exist_returns_none() {
         //arrange
@@ -227,11 +812,24 @@ mod habit_store_tests {
     fn create_habit_in_store(store: &mut HabitStore) -> &Habit {
         // arrange
         let draft = HabitDraft::new((3..25).fake::<String>(), 700.0, (3..15).fake::<String>());
-        let habit_id = store.create(draft);
-        store
-            .get(habit_id)
-            .expect("Failed to retrieve habit")
-            .to_owned()
+        let habit_id = store.create(draft).expect("Failed to create habit.");
+        store.get(habit_id).expect("Failed to retrieve habit")
+    }
+
+    fn create_logged_habit(
+        store: &mut HabitStore,
+        store_log: &mut HabitLogStore,
+    ) -> (HabitId, u32) {
+        let habit_id = create_habit_in_store(store).id;
+        let log_id = store_log
+            .add(
+                HabitLog::new(habit_id, 1.0, None),
+                store,
+                Local::today().naive_local(),
+                false,
+            )
+            .expect("Failed to log habit.");
+        (habit_id, log_id)
     }
 
     // #[test]
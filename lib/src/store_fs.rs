@@ -1,89 +1,338 @@
-extern crate dirs;
+use crate::backend::StorageBackend;
+use crate::data_dir::{create_data_dir, data_dir};
+use crate::error::{HabytError, HabytResult};
+use crate::lock::StoreLock;
+use crate::migrations::{
+    from_versioned_yaml, to_versioned_yaml, Migration, HABIT_LOG_STORE_MIGRATIONS,
+    HABIT_STORE_MIGRATIONS,
+};
 use crate::store::{HabitLogStore, HabitStore};
 
+use chrono::Local;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-const HABYT_DIR: &str = ".habyt";
 const HABIT_STORE: &str = "habit_store.yaml";
 const HABIT_LOG_STORE: &str = "habit_log_store.yaml";
+const BACKUPS_DIR: &str = "backups";
+const BACKUP_FORMAT: &str = "%Y-%m-%dT%H%M%S%.3f";
 
+/// How many backups are kept around before the oldest ones are dropped
+pub const DEFAULT_BACKUPS_KEPT: usize = 10;
+
+/// Keeps the habits and logs as YAML files in the data directory, see `data_dir`
+///
+/// Every save writes to a temporary file that's then renamed over the live one, so a crash
+/// never leaves a half-written store behind. Before that, both files are copied into a
+/// timestamped folder under `backups/`, of which the latest few are kept.
 pub struct HabitStoreFs {
-    pub store: HabitStore,
-    pub store_log: HabitLogStore,
+    store_dir: PathBuf,
     store_file: PathBuf,
     store_file_log: PathBuf,
+    backups_dir: PathBuf,
+    backups_kept: usize,
+    /// Whether `open` had to create the store files
+    created: bool,
 }
 
 impl HabitStoreFs {
-    pub fn new() -> HabitStoreFs {
+    pub fn new() -> HabytResult<HabitStoreFs> {
         // Create the directory to maintain the entries
-        let mut store_dir = dirs::home_dir().unwrap();
-        store_dir.push(HABYT_DIR);
-        if !store_dir.is_dir() {
-            println!("Welcome to Habyt!\n");
-            fs::create_dir(&store_dir).unwrap();
-        }
+        let store_dir = data_dir(None, None)?;
+        create_data_dir(&store_dir)?;
+        HabitStoreFs::open(&store_dir)
+    }
 
+    /// Keep the store files in `store_dir` instead of the default data directory
+    pub fn open(store_dir: &Path) -> HabytResult<HabitStoreFs> {
         // Creat a file to log the habits
-        let mut store_file = store_dir.clone();
-        store_file.push(HABIT_STORE);
-
-        let mut store_file_log = store_dir;
-        store_file_log.push(HABIT_LOG_STORE);
-
-        if !store_file.is_file() {
-            fs::File::create(&store_file).unwrap();
-            let content =
-                serde_yaml::to_string(&HabitStore::new()).expect("Failed to serialize tickets");
-            fs::write(&store_file, content).expect("Failed to write tickets to disk.");
-
-            fs::File::create(&store_file_log).unwrap();
-            let content =
-                serde_yaml::to_string(&HabitLogStore::new()).expect("Failed to serialize tickets");
-            fs::write(&store_file_log, content).expect("Failed to write tickets to disk.");
-
-            println!(
-                "Created {}. This file will list your currently tracked habits.",
-                store_file.to_str().unwrap()
-            );
+        let store_file = store_dir.join(HABIT_STORE);
+        let store_file_log = store_dir.join(HABIT_LOG_STORE);
+
+        let created = !store_file.is_file();
+        if created {
+            write_atomically(
+                &store_file,
+                &to_versioned_yaml(&HabitStore::new(), HABIT_STORE_MIGRATIONS)?,
+            )?;
+            write_atomically(
+                &store_file_log,
+                &to_versioned_yaml(&HabitLogStore::new(), HABIT_LOG_STORE_MIGRATIONS)?,
+            )?;
         }
 
-        HabitStoreFs {
-            store: HabitStore::new(),
-            store_log: HabitLogStore::new(),
+        Ok(HabitStoreFs {
+            store_dir: store_dir.to_path_buf(),
             store_file,
             store_file_log,
+            backups_dir: store_dir.join(BACKUPS_DIR),
+            backups_kept: DEFAULT_BACKUPS_KEPT,
+            created,
+        })
+    }
+
+    /// The habit store file, when `open` just created it, for a first-run notice
+    pub fn created(&self) -> Option<&Path> {
+        self.created.then_some(self.store_file.as_path())
+    }
+
+    /// Keep `backups_kept` backups instead of `DEFAULT_BACKUPS_KEPT`, none at all with 0
+    pub fn with_backups(mut self, backups_kept: usize) -> HabitStoreFs {
+        self.backups_kept = backups_kept;
+        self
+    }
+
+    /// Copy both store files into a new backup, dropping the oldest ones beyond the limit
+    fn back_up(&self) -> HabytResult<()> {
+        if self.backups_kept == 0 {
+            return Ok(());
+        }
+
+        let backup_dir = self
+            .backups_dir
+            .join(Local::now().format(BACKUP_FORMAT).to_string());
+        fs::create_dir_all(&backup_dir)?;
+        for file in [&self.store_file, &self.store_file_log].iter() {
+            if let Some(name) = file.file_name().filter(|_| file.is_file()) {
+                fs::copy(file, backup_dir.join(name))?;
+            }
+        }
+
+        for stale in self.backups()?.iter().skip(self.backups_kept) {
+            fs::remove_dir_all(self.backups_dir.join(stale))?;
+        }
+        Ok(())
+    }
+}
+
+/// Replace `file` with `content` without ever leaving it half-written.
+///
+/// The content goes to a temporary file first, which is synced to disk and then renamed
+/// over `file`, so a crash or a full disk leaves either the old or the new content behind.
+fn write_atomically(file: &Path, content: &str) -> HabytResult<()> {
+    let mut temp_file = file.as_os_str().to_owned();
+    temp_file.push(".tmp");
+    let temp_file = PathBuf::from(temp_file);
+
+    let written = fs::File::create(&temp_file).and_then(|mut temp| {
+        temp.write_all(content.as_bytes())?;
+        temp.sync_all()
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_file);
+        return Err(e.into());
+    }
+    fs::rename(&temp_file, file)?;
+
+    // Make the rename itself durable
+    #[cfg(unix)]
+    {
+        if let Some(dir) = file.parent() {
+            fs::File::open(dir)?.sync_all()?;
+        }
+    }
+    Ok(())
+}
+
+/// Read a store file, falling back to an empty store when it does not exist yet.
+///
+/// Files written by an older `habyt` are upgraded in place, keeping a copy of the original
+/// next to them, e.g. `habit_store.yaml.v0.bak`.
+fn read_store<T: Serialize + DeserializeOwned>(
+    file: &Path,
+    migrations: &[Migration],
+    default: T,
+) -> HabytResult<T> {
+    let data = match fs::read_to_string(file) {
+        Ok(data) => data,
+        Err(e) => {
+            return match e.kind() {
+                std::io::ErrorKind::NotFound => Ok(default),
+                _ => Err(e.into()),
+            }
+        }
+    };
+
+    let migrated = from_versioned_yaml::<T>(&data, migrations)?;
+    if let Some(version) = migrated.upgraded_from {
+        let mut backup = file.as_os_str().to_owned();
+        backup.push(format!(".v{}.bak", version));
+        fs::copy(file, &backup)?;
+        write_atomically(file, &to_versioned_yaml(&migrated.store, migrations)?)?;
+    }
+    Ok(migrated.store)
+}
+
+impl StorageBackend for HabitStoreFs {
+    fn lock(&self, timeout: Duration) -> HabytResult<StoreLock> {
+        StoreLock::acquire(&self.store_dir, timeout)
+    }
+
+    fn load(&mut self) -> HabytResult<HabitStore> {
+        read_store(&self.store_file, HABIT_STORE_MIGRATIONS, HabitStore::new())
+    }
+
+    fn save(&mut self, store: &HabitStore) -> HabytResult<()> {
+        let content = to_versioned_yaml(store, HABIT_STORE_MIGRATIONS)?;
+        self.back_up()?;
+        write_atomically(&self.store_file, &content)
+    }
+
+    fn load_log(&mut self) -> HabytResult<HabitLogStore> {
+        read_store(
+            &self.store_file_log,
+            HABIT_LOG_STORE_MIGRATIONS,
+            HabitLogStore::new(),
+        )
+    }
+
+    fn save_log(&mut self, store_log: &HabitLogStore) -> HabytResult<()> {
+        let content = to_versioned_yaml(store_log, HABIT_LOG_STORE_MIGRATIONS)?;
+        self.back_up()?;
+        write_atomically(&self.store_file_log, &content)
+    }
+
+    fn commit(&mut self, store: &HabitStore, store_log: &HabitLogStore) -> HabytResult<()> {
+        // Serialize both up front, so a failure leaves neither file half-updated
+        let content = to_versioned_yaml(store, HABIT_STORE_MIGRATIONS)?;
+        let content_log = to_versioned_yaml(store_log, HABIT_LOG_STORE_MIGRATIONS)?;
+        self.back_up()?;
+        write_atomically(&self.store_file, &content)?;
+        write_atomically(&self.store_file_log, &content_log)
+    }
+
+    fn backups(&self) -> HabytResult<Vec<String>> {
+        let entries = match fs::read_dir(&self.backups_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut backups = vec![];
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                backups.push(entry.file_name().to_string_lossy().into_owned());
+            }
         }
+        // The timestamps sort chronologically, newest goes first
+        backups.sort_by(|a, b| b.cmp(a));
+        Ok(backups)
     }
 
-    pub fn load(&mut self) {
-        self.store = match fs::read_to_string(&self.store_file) {
-            Ok(data) => serde_yaml::from_str(&data).expect("Failed to parse serialised data."),
-            Err(e) => match e.kind() {
-                std::io::ErrorKind::NotFound => HabitStore::new(),
-                _ => panic!("Failed to read data."),
-            },
+    fn restore_backup(&mut self, name: &str) -> HabytResult<()> {
+        let backup_dir = self.backups_dir.join(name);
+        if name.is_empty() || name.contains(['/', '\\']) || !backup_dir.is_dir() {
+            return Err(HabytError::NotFound(format!(
+                "There's no backup named {}",
+                name
+            )));
+        }
+
+        // Read the backup before backing up the current files, which may drop it
+        let mut restored = vec![];
+        for file in [&self.store_file, &self.store_file_log].iter() {
+            if let Some(name) = file.file_name() {
+                let backed_up = backup_dir.join(name);
+                if backed_up.is_file() {
+                    restored.push((file.to_path_buf(), fs::read_to_string(backed_up)?));
+                }
+            }
+        }
+
+        // What's there now gets backed up too, so the restore itself can be undone
+        self.back_up()?;
+        for (file, content) in restored.iter() {
+            write_atomically(file, content)?;
         }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod habit_store_fs_tests {
+    use crate::backend::StorageBackend;
+    use crate::models::HabitDraft;
+    use crate::store_fs::HabitStoreFs;
+    use std::fs;
+
+    fn create_habit(backend: &mut HabitStoreFs, name: &str) {
+        let mut store = backend.load().expect("Failed to load habits.");
+        store
+            .create(HabitDraft::new(name.into(), 1.0, "".into()))
+            .expect("Failed to create habit.");
+        backend.save(&store).expect("Failed to save habits.");
     }
 
-    pub fn save(&self) {
-        let content = serde_yaml::to_string(&self.store).expect("Failed to serialize tickets");
-        fs::write(&self.store_file, content).expect("Failed to write tickets to disk.")
+    #[test]
+    fn saving_leaves_no_temporary_files_behind() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let mut backend = HabitStoreFs::open(dir.path()).unwrap();
+
+        // act
+        create_habit(&mut backend, "writing");
+
+        // assert
+        let leftovers = fs::read_dir(dir.path())
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().ends_with(".tmp")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+        assert_eq!(backend.load().unwrap().list().len(), 1);
     }
 
-    pub fn load_log(&mut self) {
-        self.store_log = match fs::read_to_string(&self.store_file_log) {
-            Ok(data) => serde_yaml::from_str(&data).expect("Failed to parse serialised data."),
-            Err(e) => match e.kind() {
-                std::io::ErrorKind::NotFound => HabitLogStore::new(),
-                _ => panic!("Failed to read data."),
-            },
+    #[test]
+    fn only_the_latest_backups_are_kept() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let mut backend = HabitStoreFs::open(dir.path()).unwrap().with_backups(2);
+
+        // act
+        for name in ["reading", "writing", "running"].iter() {
+            create_habit(&mut backend, name);
+            std::thread::sleep(std::time::Duration::from_millis(5));
         }
+
+        // assert
+        assert_eq!(backend.backups().unwrap().len(), 2);
     }
 
-    pub fn save_log(&self) {
-        let content = serde_yaml::to_string(&self.store_log).expect("Failed to serialize tickets");
-        fs::write(&self.store_file_log, content).expect("Failed to write tickets to disk.")
+    #[test]
+    fn restoring_a_backup_brings_back_the_store_as_it_was() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let mut backend = HabitStoreFs::open(dir.path()).unwrap();
+        create_habit(&mut backend, "reading");
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        create_habit(&mut backend, "writing");
+        let before_writing = backend.backups().unwrap()[0].clone();
+
+        // act
+        backend
+            .restore_backup(&before_writing)
+            .expect("Failed to restore backup.");
+
+        // assert
+        assert_eq!(backend.load().unwrap().list().len(), 1);
+    }
+
+    #[test]
+    fn restoring_a_backup_that_does_not_exist_should_fail() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let mut backend = HabitStoreFs::open(dir.path()).unwrap();
+
+        // act
+        let restored = backend.restore_backup("../elsewhere");
+
+        // assert
+        assert!(restored.is_err());
     }
 }
@@ -0,0 +1,104 @@
+use crate::backend::StorageBackend;
+use crate::error::HabytResult;
+use crate::store::{HabitLogStore, HabitStore};
+
+/// Keeps the habits and logs in memory, never touching the disk
+#[derive(Default)]
+pub struct HabitStoreMem {
+    store: HabitStore,
+    store_log: HabitLogStore,
+}
+
+impl HabitStoreMem {
+    pub fn new() -> HabitStoreMem {
+        HabitStoreMem::default()
+    }
+}
+
+impl StorageBackend for HabitStoreMem {
+    fn load(&mut self) -> HabytResult<HabitStore> {
+        Ok(self.store.clone())
+    }
+
+    fn save(&mut self, store: &HabitStore) -> HabytResult<()> {
+        self.store = store.clone();
+        Ok(())
+    }
+
+    fn load_log(&mut self) -> HabytResult<HabitLogStore> {
+        Ok(self.store_log.clone())
+    }
+
+    fn save_log(&mut self, store_log: &HabitLogStore) -> HabytResult<()> {
+        self.store_log = store_log.clone();
+        Ok(())
+    }
+
+    fn commit(&mut self, store: &HabitStore, store_log: &HabitLogStore) -> HabytResult<()> {
+        self.store = store.clone();
+        self.store_log = store_log.clone();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod habit_store_mem_tests {
+    use crate::backend::StorageBackend;
+    use crate::models::{HabitDraft, HabitLog};
+    use crate::store_mem::HabitStoreMem;
+    use chrono::Local;
+    use fake::Fake;
+
+    #[test]
+    fn committed_habits_and_logs_can_be_loaded_back() {
+        // arrange
+        let mut backend = HabitStoreMem::new();
+        let mut store = backend.load().expect("Failed to load habits.");
+        let mut store_log = backend.load_log().expect("Failed to load logs.");
+        let draft = HabitDraft::new((3..25).fake::<String>(), 700.0, (3..15).fake::<String>());
+        let habit_id = store.create(draft).expect("Failed to create habit.");
+        let log_id = store_log
+            .add(
+                HabitLog::new(habit_id, 350.0, None),
+                &store,
+                Local::today().naive_local(),
+                false,
+            )
+            .expect("Failed to log habit.");
+
+        // act
+        backend
+            .commit(&store, &store_log)
+            .expect("Failed to commit.");
+
+        // assert
+        assert!(backend.load().unwrap().get(habit_id).is_some());
+        assert_eq!(
+            backend
+                .load_log()
+                .unwrap()
+                .get(log_id)
+                .map(|log_entry| log_entry.quantum),
+            Some(350.0)
+        );
+    }
+
+    #[test]
+    fn unsaved_changes_are_not_visible_to_the_backend() {
+        // arrange
+        let mut backend = HabitStoreMem::new();
+        let mut store = backend.load().expect("Failed to load habits.");
+
+        // act
+        store
+            .create(HabitDraft::new(
+                (3..25).fake::<String>(),
+                700.0,
+                (3..15).fake::<String>(),
+            ))
+            .expect("Failed to create habit.");
+
+        // assert
+        assert!(backend.load().unwrap().list().is_empty());
+    }
+}
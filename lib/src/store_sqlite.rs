@@ -0,0 +1,813 @@
+use crate::backend::StorageBackend;
+use crate::data_dir::{create_data_dir, data_dir};
+use crate::error::{HabytError, HabytResult};
+use crate::lock::StoreLock;
+use crate::models::{Habit, HabitId, HabitLog, HabitName, HabitUnit, Pause, Schedule, Tombstone};
+use crate::store::{HabitLogStore, HabitStore};
+use crate::store_fs::HabitStoreFs;
+
+use chrono::{NaiveDate, NaiveDateTime, Weekday};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const HABIT_DB: &str = "habyt.db";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+/// The schema version this release writes, kept in `PRAGMA user_version`
+const SCHEMA_VERSION: i32 = 1;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS store_meta (
+        key TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS habits (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        quantum REAL NOT NULL,
+        unit TEXT NOT NULL,
+        notes TEXT NOT NULL,
+        streak TEXT NOT NULL,
+        difficulty TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        active INTEGER NOT NULL,
+        archived INTEGER NOT NULL,
+        created_at TEXT,
+        updated_at TEXT,
+        archived_at TEXT,
+        schedule TEXT,
+        schedule_weekdays TEXT,
+        schedule_count INTEGER,
+        schedule_from TEXT
+    );
+    CREATE TABLE IF NOT EXISTS habit_pauses (
+        habit_id INTEGER NOT NULL,
+        from_date TEXT NOT NULL,
+        until_date TEXT
+    );
+    CREATE INDEX IF NOT EXISTS habit_pauses_habit_id ON habit_pauses (habit_id);
+    CREATE TABLE IF NOT EXISTS tombstones (
+        id INTEGER PRIMARY KEY,
+        name TEXT,
+        deleted_at TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS habit_logs (
+        id INTEGER PRIMARY KEY,
+        habit_id INTEGER NOT NULL,
+        quantum REAL NOT NULL,
+        notes TEXT NOT NULL,
+        date TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS habit_logs_habit_id ON habit_logs (habit_id);
+    CREATE INDEX IF NOT EXISTS habit_logs_date ON habit_logs (date);
+    CREATE TABLE IF NOT EXISTS archived_habit_logs (
+        id INTEGER PRIMARY KEY,
+        habit_id INTEGER NOT NULL,
+        quantum REAL NOT NULL,
+        notes TEXT NOT NULL,
+        date TEXT NOT NULL
+    );
+";
+
+/// Keeps the habits and logs in an SQLite database, `habyt.db` in the data directory
+///
+/// Habits are few, so saving rewrites them, along with their pauses and tombstones. Logs pile
+/// up every day, so saving only touches the rows `HabitLogStore` says changed instead of
+/// rewriting the whole history. The schema is versioned with `PRAGMA user_version`, and
+/// upgraded when the database is opened.
+pub struct HabitStoreSqlite {
+    conn: Connection,
+    /// Where the database lives, to lock it from other processes; none when it's in memory
+    db_dir: Option<PathBuf>,
+}
+
+impl HabitStoreSqlite {
+    pub fn new() -> HabytResult<HabitStoreSqlite> {
+        let dir = data_dir(None, None)?;
+        create_data_dir(&dir)?;
+        HabitStoreSqlite::open_in(&dir)
+    }
+
+    /// Keep `habyt.db` in `dir` instead of the default data directory
+    pub fn open_in(dir: &Path) -> HabytResult<HabitStoreSqlite> {
+        HabitStoreSqlite::open(&dir.join(HABIT_DB))
+    }
+
+    pub fn open(path: &Path) -> HabytResult<HabitStoreSqlite> {
+        let db_dir = path.parent().map(|dir| dir.to_path_buf());
+        HabitStoreSqlite::with_connection(Connection::open(path)?, db_dir)
+    }
+
+    pub fn open_in_memory() -> HabytResult<HabitStoreSqlite> {
+        HabitStoreSqlite::with_connection(Connection::open_in_memory()?, None)
+    }
+
+    fn with_connection(
+        mut conn: Connection,
+        db_dir: Option<PathBuf>,
+    ) -> HabytResult<HabitStoreSqlite> {
+        HabitStoreSqlite::migrate(&mut conn)?;
+        Ok(HabitStoreSqlite { conn, db_dir })
+    }
+
+    /// Bring the schema up to `SCHEMA_VERSION`, refusing databases of a newer habyt
+    fn migrate(conn: &mut Connection) -> HabytResult<()> {
+        let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            return Err(HabytError::Conflict(format!(
+                "The database was written by a newer habyt (version {}, this one reads up to {})",
+                version, SCHEMA_VERSION
+            )));
+        }
+        if version == SCHEMA_VERSION {
+            return Ok(());
+        }
+
+        let tx = conn.transaction()?;
+        // Version 0 kept each habit, archived habit and tombstone as a YAML body
+        let legacy: bool = tx.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('habits') WHERE name = 'body'",
+            [],
+            |row| row.get(0),
+        )?;
+        let legacy_store = if legacy {
+            let current_id = HabitStoreSqlite::read_current_id(&tx, "habits")?;
+            let store = HabitStore::from_parts(
+                current_id,
+                HabitStoreSqlite::read_bodies(&tx, "habits")?,
+                HabitStoreSqlite::read_bodies(&tx, "archived_habits")?,
+                HabitStoreSqlite::read_bodies(&tx, "tombstones")?,
+            );
+            tx.execute_batch(
+                "DROP TABLE habits; DROP TABLE archived_habits; DROP TABLE tombstones;",
+            )?;
+            Some(store)
+        } else {
+            None
+        };
+        tx.execute_batch(SCHEMA)?;
+        if let Some(store) = legacy_store {
+            HabitStoreSqlite::write_habits(&tx, &store)?;
+        }
+        tx.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(tx.commit()?)
+    }
+
+    /// One-shot import of the YAML store files into an empty database.
+    ///
+    /// Returns the number of habits and log entries imported.
+    pub fn import_yaml(&mut self, yaml: &mut HabitStoreFs) -> HabytResult<(usize, usize)> {
+        if !self.load()?.list().is_empty() || !self.load_log()?.list().is_empty() {
+            return Err(HabytError::Conflict(
+                "The SQLite store already has habits, refusing to overwrite them".into(),
+            ));
+        }
+        let store = yaml.load()?;
+        let mut store_log = yaml.load_log()?;
+        store_log.touch_all();
+        self.commit(&store, &store_log)?;
+        Ok((store.list().len(), store_log.list().len()))
+    }
+
+    fn read_current_id(conn: &Connection, key: &str) -> HabytResult<u32> {
+        let current_id = conn
+            .query_row(
+                "SELECT value FROM store_meta WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(current_id.unwrap_or(0))
+    }
+
+    fn write_habits(conn: &Connection, store: &HabitStore) -> HabytResult<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO store_meta (key, value) VALUES ('habits', ?1)",
+            params![store.current_id()],
+        )?;
+        conn.execute_batch(
+            "DELETE FROM habits; DELETE FROM habit_pauses; DELETE FROM tombstones;",
+        )?;
+
+        let (archive, tombstones) = store.graveyard();
+        let habits = store
+            .list()
+            .into_iter()
+            .map(|habit| (habit, false))
+            .chain(archive.into_iter().map(|habit| (habit, true)));
+        let mut insert = conn.prepare(
+            "INSERT INTO habits (id, name, quantum, unit, notes, streak, difficulty, kind, active,
+                 archived, created_at, updated_at, archived_at, schedule, schedule_weekdays,
+                 schedule_count, schedule_from)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+        )?;
+        let mut insert_pause = conn.prepare(
+            "INSERT INTO habit_pauses (habit_id, from_date, until_date) VALUES (?1, ?2, ?3)",
+        )?;
+        for (habit, archived) in habits {
+            let (schedule, weekdays, count, from) = match &habit.schedule {
+                None => (None, None, None, None),
+                Some(Schedule::Weekdays(weekdays)) => (
+                    Some("Weekdays"),
+                    Some(
+                        weekdays
+                            .iter()
+                            .map(|day| day.to_string())
+                            .collect::<Vec<_>>()
+                            .join(","),
+                    ),
+                    None,
+                    None,
+                ),
+                Some(Schedule::TimesPer(times)) => (Some("TimesPer"), None, Some(*times), None),
+                Some(Schedule::EveryNDays { days, from }) => (
+                    Some("EveryNDays"),
+                    None,
+                    Some(*days),
+                    Some(from.format(DATE_FORMAT).to_string()),
+                ),
+            };
+            insert.execute(params![
+                habit.id,
+                habit.name.0,
+                habit.quantum,
+                habit.unit.0,
+                habit.notes,
+                to_text(&habit.streak)?,
+                to_text(&habit.difficulty)?,
+                to_text(&habit.kind)?,
+                habit.active,
+                archived,
+                habit.created_at.map(datetime_text),
+                habit.updated_at.map(datetime_text),
+                habit.archived_at.map(datetime_text),
+                schedule,
+                weekdays,
+                count,
+                from,
+            ])?;
+            for pause in habit.pauses.iter() {
+                insert_pause.execute(params![
+                    habit.id,
+                    pause.from.format(DATE_FORMAT).to_string(),
+                    pause
+                        .until
+                        .map(|until| until.format(DATE_FORMAT).to_string()),
+                ])?;
+            }
+        }
+
+        let mut insert =
+            conn.prepare("INSERT INTO tombstones (id, name, deleted_at) VALUES (?1, ?2, ?3)")?;
+        for tombstone in tombstones {
+            insert.execute(params![
+                tombstone.id,
+                tombstone.name.as_ref().map(|name| name.0.clone()),
+                datetime_text(tombstone.deleted_at),
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// The habits, or the archived ones, with their pauses
+    fn read_habits(conn: &Connection, archived: bool) -> HabytResult<Vec<Habit>> {
+        let mut select = conn.prepare(
+            "SELECT id, name, quantum, unit, notes, streak, difficulty, kind, active, created_at,
+                 updated_at, archived_at, schedule, schedule_weekdays, schedule_count,
+                 schedule_from
+             FROM habits WHERE archived = ?1 ORDER BY id",
+        )?;
+        let rows = select
+            .query_map(params![archived], |row| {
+                Ok(HabitRow {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    quantum: row.get(2)?,
+                    unit: row.get(3)?,
+                    notes: row.get(4)?,
+                    streak: row.get(5)?,
+                    difficulty: row.get(6)?,
+                    kind: row.get(7)?,
+                    active: row.get(8)?,
+                    created_at: row.get(9)?,
+                    updated_at: row.get(10)?,
+                    archived_at: row.get(11)?,
+                    schedule: row.get(12)?,
+                    schedule_weekdays: row.get(13)?,
+                    schedule_count: row.get(14)?,
+                    schedule_from: row.get(15)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut select_pauses = conn.prepare(
+            "SELECT from_date, until_date FROM habit_pauses WHERE habit_id = ?1 ORDER BY from_date",
+        )?;
+        rows.into_iter()
+            .map(|row| {
+                let pauses = select_pauses
+                    .query_map(params![row.id], |pause| {
+                        Ok((
+                            pause.get::<_, String>(0)?,
+                            pause.get::<_, Option<String>>(1)?,
+                        ))
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?
+                    .into_iter()
+                    .map(|(from, until)| {
+                        Ok(Pause {
+                            from: date(&from)?,
+                            until: until.as_deref().map(date).transpose()?,
+                        })
+                    })
+                    .collect::<HabytResult<Vec<_>>>()?;
+                row.into_habit(pauses)
+            })
+            .collect()
+    }
+
+    fn read_tombstones(conn: &Connection) -> HabytResult<Vec<Tombstone>> {
+        let mut select = conn.prepare("SELECT id, name, deleted_at FROM tombstones ORDER BY id")?;
+        let rows = select
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, HabitId>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows.into_iter()
+            .map(|(id, name, deleted_at)| {
+                Ok(Tombstone {
+                    id,
+                    name: name.map(HabitName),
+                    deleted_at: datetime(&deleted_at)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Habits as version 0 kept them, only read to upgrade the schema
+    fn read_bodies<T: DeserializeOwned>(conn: &Connection, table: &str) -> HabytResult<Vec<T>> {
+        let mut select = conn.prepare(&format!("SELECT body FROM {} ORDER BY id", table))?;
+        let bodies = select
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        bodies
+            .iter()
+            .map(|body| Ok(serde_yaml::from_str(body)?))
+            .collect()
+    }
+
+    fn write_logs(conn: &Connection, store_log: &HabitLogStore) -> HabytResult<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO store_meta (key, value) VALUES ('habit_logs', ?1)",
+            params![store_log.current_id()],
+        )?;
+
+        // Only what changed since the logs were loaded is written
+        let (changed, removed) = store_log.changes();
+        let mut delete = conn.prepare("DELETE FROM habit_logs WHERE id = ?1")?;
+        for id in removed {
+            delete.execute(params![id])?;
+        }
+        let mut upsert = conn.prepare(
+            "INSERT OR REPLACE INTO habit_logs (id, habit_id, quantum, notes, date)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for (id, log_entry) in changed {
+            upsert.execute(params![
+                id,
+                log_entry.habit_id,
+                log_entry.quantum,
+                log_entry.notes,
+                log_entry.date.format(DATE_FORMAT).to_string()
+            ])?;
+        }
+
+        // Archived logs hardly ever change, they're simply rewritten
+        conn.execute("DELETE FROM archived_habit_logs", [])?;
+        let mut insert = conn.prepare(
+            "INSERT INTO archived_habit_logs (id, habit_id, quantum, notes, date)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for (id, log_entry) in store_log.archived_entries() {
+            insert.execute(params![
+                id,
+                log_entry.habit_id,
+                log_entry.quantum,
+                log_entry.notes,
+                log_entry.date.format(DATE_FORMAT).to_string()
+            ])?;
+        }
+        Ok(())
+    }
+
+    fn read_logs(conn: &Connection, table: &str) -> HabytResult<Vec<(u32, HabitLog)>> {
+        let mut select = conn.prepare(&format!(
+            "SELECT id, habit_id, quantum, notes, date FROM {} ORDER BY id",
+            table
+        ))?;
+        let rows = select
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, u32>(0)?,
+                    row.get::<_, u32>(1)?,
+                    row.get::<_, f64>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        rows.into_iter()
+            .map(|(id, habit_id, quantum, notes, date)| {
+                let date = NaiveDate::parse_from_str(&date, DATE_FORMAT)
+                    .map_err(|e| HabytError::Parse(format!("log {} has a bad date: {}", id, e)))?;
+                Ok((
+                    id,
+                    HabitLog {
+                        id,
+                        habit_id,
+                        quantum,
+                        notes,
+                        date,
+                    },
+                ))
+            })
+            .collect()
+    }
+}
+
+/// A row of the `habits` table, before its text columns are read back into a `Habit`
+struct HabitRow {
+    id: HabitId,
+    name: String,
+    quantum: f64,
+    unit: String,
+    notes: String,
+    streak: String,
+    difficulty: String,
+    kind: String,
+    active: bool,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    archived_at: Option<String>,
+    schedule: Option<String>,
+    schedule_weekdays: Option<String>,
+    schedule_count: Option<u32>,
+    schedule_from: Option<String>,
+}
+
+impl HabitRow {
+    fn into_habit(self, pauses: Vec<Pause>) -> HabytResult<Habit> {
+        let bad = |what: &str| HabytError::Parse(format!("habit {} has a bad {}", self.id, what));
+        let schedule = match self.schedule.as_deref() {
+            None => None,
+            Some("Weekdays") => Some(Schedule::Weekdays(
+                self.schedule_weekdays
+                    .as_deref()
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|day| day.parse::<Weekday>().map_err(|_| bad("schedule")))
+                    .collect::<HabytResult<Vec<_>>>()?,
+            )),
+            Some("TimesPer") => Some(Schedule::TimesPer(
+                self.schedule_count.ok_or_else(|| bad("schedule"))?,
+            )),
+            Some("EveryNDays") => Some(Schedule::EveryNDays {
+                days: self.schedule_count.ok_or_else(|| bad("schedule"))?,
+                from: date(
+                    self.schedule_from
+                        .as_deref()
+                        .ok_or_else(|| bad("schedule"))?,
+                )?,
+            }),
+            Some(_) => return Err(bad("schedule")),
+        };
+        Ok(Habit {
+            id: self.id,
+            name: HabitName(self.name),
+            quantum: self.quantum,
+            unit: HabitUnit(self.unit),
+            notes: self.notes,
+            streak: from_text(&self.streak)?,
+            difficulty: from_text(&self.difficulty)?,
+            kind: from_text(&self.kind)?,
+            active: self.active,
+            created_at: self.created_at.as_deref().map(datetime).transpose()?,
+            updated_at: self.updated_at.as_deref().map(datetime).transpose()?,
+            archived_at: self.archived_at.as_deref().map(datetime).transpose()?,
+            pauses,
+            schedule,
+        })
+    }
+}
+
+/// An enum such as `Streak::Daily` as the text of its variant, "Daily"
+fn to_text<T: Serialize>(value: &T) -> HabytResult<String> {
+    match serde_json::to_value(value).map_err(|e| HabytError::Parse(e.to_string()))? {
+        serde_json::Value::String(text) => Ok(text),
+        other => Ok(other.to_string()),
+    }
+}
+
+fn from_text<T: DeserializeOwned>(text: &str) -> HabytResult<T> {
+    serde_json::from_value(serde_json::Value::String(text.into()))
+        .map_err(|e| HabytError::Parse(e.to_string()))
+}
+
+fn datetime_text(datetime: NaiveDateTime) -> String {
+    datetime.format(DATETIME_FORMAT).to_string()
+}
+
+fn datetime(text: &str) -> HabytResult<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(text, DATETIME_FORMAT)
+        .map_err(|e| HabytError::Parse(format!("'{}' is not a date and time: {}", text, e)))
+}
+
+fn date(text: &str) -> HabytResult<NaiveDate> {
+    NaiveDate::parse_from_str(text, DATE_FORMAT)
+        .map_err(|e| HabytError::Parse(format!("'{}' is not a date: {}", text, e)))
+}
+
+impl StorageBackend for HabitStoreSqlite {
+    fn lock(&self, timeout: Duration) -> HabytResult<StoreLock> {
+        match &self.db_dir {
+            Some(db_dir) => StoreLock::acquire(db_dir, timeout),
+            None => Ok(StoreLock::unlocked()),
+        }
+    }
+
+    fn load(&mut self) -> HabytResult<HabitStore> {
+        Ok(HabitStore::from_parts(
+            HabitStoreSqlite::read_current_id(&self.conn, "habits")?,
+            HabitStoreSqlite::read_habits(&self.conn, false)?,
+            HabitStoreSqlite::read_habits(&self.conn, true)?,
+            HabitStoreSqlite::read_tombstones(&self.conn)?,
+        ))
+    }
+
+    fn save(&mut self, store: &HabitStore) -> HabytResult<()> {
+        let tx = self.conn.transaction()?;
+        HabitStoreSqlite::write_habits(&tx, store)?;
+        Ok(tx.commit()?)
+    }
+
+    fn load_log(&mut self) -> HabytResult<HabitLogStore> {
+        Ok(HabitLogStore::from_parts(
+            HabitStoreSqlite::read_current_id(&self.conn, "habit_logs")?,
+            HabitStoreSqlite::read_logs(&self.conn, "habit_logs")?,
+            HabitStoreSqlite::read_logs(&self.conn, "archived_habit_logs")?,
+        ))
+    }
+
+    fn save_log(&mut self, store_log: &HabitLogStore) -> HabytResult<()> {
+        let tx = self.conn.transaction()?;
+        HabitStoreSqlite::write_logs(&tx, store_log)?;
+        Ok(tx.commit()?)
+    }
+
+    fn commit(&mut self, store: &HabitStore, store_log: &HabitLogStore) -> HabytResult<()> {
+        let tx = self.conn.transaction()?;
+        HabitStoreSqlite::write_habits(&tx, store)?;
+        HabitStoreSqlite::write_logs(&tx, store_log)?;
+        Ok(tx.commit()?)
+    }
+}
+
+#[cfg(test)]
+mod habit_store_sqlite_tests {
+    use crate::backend::StorageBackend;
+    use crate::error::HabytError;
+    use crate::models::{HabitDraft, HabitLog, Schedule};
+    use crate::store::HabitStore;
+    use crate::store_sqlite::{HabitStoreSqlite, SCHEMA_VERSION};
+    use chrono::Local;
+    use chrono::{NaiveDate, Weekday};
+    use fake::Fake;
+    use rusqlite::{params, Connection};
+
+    #[test]
+    fn committed_habits_and_logs_can_be_loaded_back() {
+        // arrange
+        let mut backend = HabitStoreSqlite::open_in_memory().expect("Failed to open database.");
+        let mut store = backend.load().expect("Failed to load habits.");
+        let mut store_log = backend.load_log().expect("Failed to load logs.");
+        let draft = HabitDraft::new((3..25).fake::<String>(), 700.0, (3..15).fake::<String>());
+        let habit_id = store
+            .create(draft.clone())
+            .expect("Failed to create habit.");
+        let log_id = store_log
+            .add(
+                HabitLog::new(habit_id, 350.0, Some("rainy".into())),
+                &store,
+                Local::today().naive_local(),
+                false,
+            )
+            .expect("Failed to log habit.");
+
+        // act
+        backend
+            .commit(&store, &store_log)
+            .expect("Failed to commit.");
+
+        // assert
+        let habit = backend
+            .load()
+            .unwrap()
+            .get(habit_id)
+            .cloned()
+            .expect("Habit was not saved.");
+        assert_eq!(habit.name, draft.name);
+        let store_log = backend.load_log().unwrap();
+        let log_entry = store_log.get(log_id).expect("Log was not saved.");
+        assert_eq!(log_entry.id, log_id);
+        assert_eq!(log_entry.habit_id, habit_id);
+        assert_eq!(log_entry.notes, "rainy");
+    }
+
+    #[test]
+    fn ids_keep_counting_after_a_reload() {
+        // arrange
+        let mut backend = HabitStoreSqlite::open_in_memory().expect("Failed to open database.");
+        let mut store = backend.load().expect("Failed to load habits.");
+        let draft = HabitDraft::new((3..25).fake::<String>(), 700.0, (3..15).fake::<String>());
+        let first_id = store
+            .create(draft.clone())
+            .expect("Failed to create habit.");
+        store.delete(first_id);
+        backend.save(&store).expect("Failed to save habits.");
+
+        // act
+        let second_id = backend
+            .load()
+            .unwrap()
+            .create(draft)
+            .expect("Failed to create habit.");
+
+        // assert
+        assert_ne!(first_id, second_id);
+    }
+
+    #[test]
+    fn removed_log_entries_are_deleted_from_the_database() {
+        // arrange
+        let mut backend = HabitStoreSqlite::open_in_memory().expect("Failed to open database.");
+        let mut store = backend.load().expect("Failed to load habits.");
+        let mut store_log = backend.load_log().expect("Failed to load logs.");
+        let draft = HabitDraft::new((3..25).fake::<String>(), 700.0, (3..15).fake::<String>());
+        let habit_id = store.create(draft).expect("Failed to create habit.");
+        store_log
+            .add(
+                HabitLog::new(habit_id, 10.0, None),
+                &store,
+                Local::today().naive_local(),
+                false,
+            )
+            .expect("Failed to log habit.");
+        backend
+            .commit(&store, &store_log)
+            .expect("Failed to commit.");
+        let mut store_log = backend.load_log().expect("Failed to load logs.");
+        let log_id = store_log.list()[0].id;
+        store_log.delete(log_id);
+
+        // act
+        backend.save_log(&store_log).expect("Failed to save logs.");
+
+        // assert
+        assert!(backend.load_log().unwrap().list().is_empty());
+    }
+
+    #[test]
+    fn log_entries_left_alone_are_not_written_again() {
+        // arrange
+        let mut backend = HabitStoreSqlite::open_in_memory().expect("Failed to open database.");
+        let mut store = backend.load().expect("Failed to load habits.");
+        let mut store_log = backend.load_log().expect("Failed to load logs.");
+        let draft = HabitDraft::new((3..25).fake::<String>(), 700.0, (3..15).fake::<String>());
+        let habit_id = store.create(draft).expect("Failed to create habit.");
+        let log_id = store_log
+            .add(
+                HabitLog::new(habit_id, 10.0, None),
+                &store,
+                Local::today().naive_local(),
+                false,
+            )
+            .expect("Failed to log habit.");
+        backend
+            .commit(&store, &store_log)
+            .expect("Failed to commit.");
+        let store_log = backend.load_log().expect("Failed to load logs.");
+        backend
+            .conn
+            .execute(
+                "UPDATE habit_logs SET notes = 'by hand' WHERE id = ?1",
+                [log_id],
+            )
+            .unwrap();
+
+        // act
+        backend.save_log(&store_log).expect("Failed to save logs.");
+
+        // assert
+        let store_log = backend.load_log().unwrap();
+        assert_eq!(store_log.get(log_id).unwrap().notes, "by hand");
+    }
+
+    #[test]
+    fn pauses_and_schedules_are_kept_in_columns() {
+        // arrange
+        let mut backend = HabitStoreSqlite::open_in_memory().expect("Failed to open database.");
+        let mut store = backend.load().expect("Failed to load habits.");
+        let mut draft = HabitDraft::new((3..25).fake::<String>(), 700.0, (3..15).fake::<String>());
+        draft.schedule = Some(Schedule::Weekdays(vec![Weekday::Mon, Weekday::Fri]));
+        let habit_id = store.create(draft).expect("Failed to create habit.");
+        store
+            .pause(
+                habit_id,
+                NaiveDate::from_ymd(2021, 6, 1),
+                Some(NaiveDate::from_ymd(2021, 6, 3)),
+            )
+            .expect("Failed to pause habit.");
+        store
+            .pause(habit_id, NaiveDate::from_ymd(2021, 7, 1), None)
+            .expect("Failed to pause habit.");
+        let habit = store.get(habit_id).cloned().unwrap();
+
+        // act
+        backend.save(&store).expect("Failed to save habits.");
+
+        // assert
+        let loaded = backend.load().unwrap().get(habit_id).cloned().unwrap();
+        assert_eq!(loaded.schedule, habit.schedule);
+        assert_eq!(loaded.pauses, habit.pauses);
+        assert_eq!(loaded.streak, habit.streak);
+        assert_eq!(loaded.created_at, habit.created_at);
+        let schedule: String = backend
+            .conn
+            .query_row("SELECT schedule_weekdays FROM habits", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(schedule, "Mon,Fri");
+    }
+
+    #[test]
+    fn a_database_of_yaml_bodies_is_upgraded() {
+        // arrange
+        let draft = HabitDraft::new((3..25).fake::<String>(), 700.0, (3..15).fake::<String>());
+        let mut store = HabitStore::new();
+        let habit_id = store
+            .create(draft.clone())
+            .expect("Failed to create habit.");
+        let body = serde_yaml::to_string(store.get(habit_id).unwrap()).unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE store_meta (key TEXT PRIMARY KEY, value INTEGER NOT NULL);
+             CREATE TABLE habits (id INTEGER PRIMARY KEY, name TEXT NOT NULL,
+                 active INTEGER NOT NULL, body TEXT NOT NULL);
+             CREATE TABLE archived_habits (id INTEGER PRIMARY KEY, body TEXT NOT NULL);
+             CREATE TABLE tombstones (id INTEGER PRIMARY KEY, body TEXT NOT NULL);
+             INSERT INTO store_meta (key, value) VALUES ('habits', 1);",
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO habits (id, name, active, body) VALUES (?1, ?2, 1, ?3)",
+            params![habit_id, draft.name.0, body],
+        )
+        .unwrap();
+
+        // act
+        let mut backend =
+            HabitStoreSqlite::with_connection(conn, None).expect("Failed to upgrade database.");
+
+        // assert
+        let store = backend.load().expect("Failed to load habits.");
+        assert_eq!(store.get(habit_id).unwrap().name, draft.name);
+        assert_eq!(store.current_id(), 1);
+        let version: i32 = backend
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn a_database_of_a_newer_habyt_is_refused() {
+        // arrange
+        let conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+
+        // act
+        let opened = HabitStoreSqlite::with_connection(conn, None);
+
+        // assert
+        assert!(matches!(opened, Err(HabytError::Conflict(_))));
+    }
+}
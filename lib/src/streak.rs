@@ -0,0 +1,453 @@
+use crate::models::{Habit, HabitLog, HasPosNeg, Schedule, Streak};
+use chrono::{Datelike, Duration, NaiveDate};
+use std::collections::BTreeMap;
+
+/// The days making up one day, ISO week or month of a habit's `Streak`
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Hash, PartialOrd, Ord)]
+pub struct Period {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl Period {
+    /// The period of the given `Streak` that `date` falls in
+    pub fn containing(streak: Streak, date: NaiveDate) -> Period {
+        match streak {
+            Streak::Daily => Period {
+                start: date,
+                end: date,
+            },
+            Streak::Weekly => {
+                let start = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+                Period {
+                    start,
+                    end: start + Duration::days(6),
+                }
+            }
+            Streak::Monthly => {
+                let start = NaiveDate::from_ymd(date.year(), date.month(), 1);
+                let next = if date.month() == 12 {
+                    NaiveDate::from_ymd(date.year() + 1, 1, 1)
+                } else {
+                    NaiveDate::from_ymd(date.year(), date.month() + 1, 1)
+                };
+                Period {
+                    start,
+                    end: next.pred(),
+                }
+            }
+        }
+    }
+
+    /// The period right before this one
+    pub fn previous(&self, streak: Streak) -> Period {
+        Period::containing(streak, self.start.pred())
+    }
+
+    /// The period right after this one
+    pub fn next(&self, streak: Streak) -> Period {
+        Period::containing(streak, self.end.succ())
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start <= date && date <= self.end
+    }
+
+    /// The days of the period, first to last
+    pub fn days(&self) -> impl Iterator<Item = NaiveDate> {
+        let end = self.end;
+        std::iter::successors(Some(self.start), |day| Some(day.succ()))
+            .take_while(move |day| *day <= end)
+    }
+}
+
+/// How well a habit has been kept up, counted in periods of its `Streak`
+#[derive(PartialEq, Debug, Clone)]
+pub struct StreakSummary {
+    /// Completed periods in a row, up to the current (or, while it's in progress, the last) one
+    pub current: u32,
+    /// The most completed periods ever in a row
+    pub longest: u32,
+    /// The most recent completed period
+    pub last_completed: Option<Period>,
+}
+
+/// The quantum logged for `habit` in each period of its `Streak`, up to and including `today`
+pub fn quantum_by_period(
+    habit: &Habit,
+    logs: &[&HabitLog],
+    today: NaiveDate,
+) -> BTreeMap<Period, f64> {
+    let mut totals = BTreeMap::new();
+    for log_entry in logs
+        .iter()
+        .filter(|log_entry| log_entry.habit_id == habit.id && log_entry.date <= today)
+    {
+        *totals
+            .entry(Period::containing(habit.streak, log_entry.date))
+            .or_insert(0.) += log_entry.quantum;
+    }
+    totals
+}
+
+/// Whether `habit` was kept up in each of its periods, oldest first, up to the current one.
+///
+/// Tracking starts with the period the habit was created in, or of its first log if that's
+/// earlier or the creation date is unknown. Periods without logs count as
+/// a success for negative habits, since nothing was over the limit, and as a miss otherwise.
+/// A positive habit's current period is left out until it's met, as it's still in progress.
+/// Periods the habit was paused in are left out too, unless something logged in them met it
+/// anyway, so a pause doesn't add clean periods to a negative habit's streak either. So are
+/// periods the habit's `Schedule` doesn't make it due in. A habit scheduled a number of times
+/// per period is met by that many days on which the quantum was met.
+pub fn period_outcomes(habit: &Habit, logs: &[&HabitLog], today: NaiveDate) -> Vec<(Period, bool)> {
+    let totals = quantum_by_period(habit, logs, today);
+    let created = habit
+        .tracked_since()
+        .map(|since| Period::containing(habit.streak, since));
+    let first = match (created, totals.keys().next()) {
+        (Some(created), Some(logged)) => created.min(*logged),
+        (Some(created), None) => created,
+        (None, Some(logged)) => *logged,
+        (None, None) => return vec![],
+    };
+
+    let times_per = match habit.schedule {
+        Some(Schedule::TimesPer(times)) => Some((times, quantum_by_day(habit, logs, today))),
+        _ => None,
+    };
+
+    let current = Period::containing(habit.streak, today);
+    let mut outcomes = vec![];
+    let mut period = first;
+    while period <= current {
+        let met = match &times_per {
+            Some((times, by_day)) => {
+                let days_met = period
+                    .days()
+                    .filter(|day| habit.is_met(by_day.get(day).copied().unwrap_or(0.)))
+                    .count();
+                days_met >= *times as usize
+            }
+            None => habit.is_met(totals.get(&period).copied().unwrap_or(0.)),
+        };
+        let due = period.days().any(|day| habit.is_due_on(day));
+        let neutral = !(met && totals.contains_key(&period))
+            && habit.is_paused_during(period.start, period.end);
+        if due && !neutral && (period != current || met || habit.is_negative()) {
+            outcomes.push((period, met));
+        }
+        period = period.next(habit.streak);
+    }
+    outcomes
+}
+
+/// The quantum logged for `habit` on each day, up to and including `today`
+pub(crate) fn quantum_by_day(
+    habit: &Habit,
+    logs: &[&HabitLog],
+    today: NaiveDate,
+) -> BTreeMap<NaiveDate, f64> {
+    let mut totals = BTreeMap::new();
+    for log_entry in logs
+        .iter()
+        .filter(|log_entry| log_entry.habit_id == habit.id && log_entry.date <= today)
+    {
+        *totals.entry(log_entry.date).or_insert(0.) += log_entry.quantum;
+    }
+    totals
+}
+
+/// Work out the streaks of `habit` from its logs, as of `today`.
+///
+/// A period is complete once the quantum logged within it meets `Habit.quantum`, see
+/// `Habit::is_met`. `logs` may hold entries of other habits too, they are left out.
+pub fn compute_streak(habit: &Habit, logs: &[&HabitLog], today: NaiveDate) -> StreakSummary {
+    let outcomes = period_outcomes(habit, logs, today);
+
+    let mut longest = 0;
+    let mut run = 0;
+    for (_, met) in outcomes.iter() {
+        run = if *met { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+
+    StreakSummary {
+        current: run,
+        longest,
+        last_completed: outcomes
+            .iter()
+            .rev()
+            .find(|(_, met)| *met)
+            .map(|(period, _)| *period),
+    }
+}
+
+#[cfg(test)]
+mod streak_tests {
+    use crate::models::{HabitLog, Kind, Schedule, Streak};
+    use crate::streak::{compute_streak, Period};
+    use crate::test_support::{day, habit, log};
+    use chrono::Weekday;
+
+    #[test]
+    fn consecutive_complete_days_make_a_streak() {
+        // arrange
+        let habit = habit(Streak::Daily, 500., Kind::Positive);
+        let logs = [
+            log("2021-06-01", 500.),
+            log("2021-06-02", 200.),
+            log("2021-06-02", 300.),
+            log("2021-06-03", 600.),
+        ];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let summary = compute_streak(&habit, &logs, day("2021-06-03"));
+
+        // assert
+        assert_eq!(summary.current, 3);
+        assert_eq!(summary.longest, 3);
+        assert_eq!(
+            summary.last_completed,
+            Some(Period::containing(Streak::Daily, day("2021-06-03")))
+        );
+    }
+
+    #[test]
+    fn an_incomplete_day_breaks_the_streak() {
+        // arrange
+        let habit = habit(Streak::Daily, 500., Kind::Positive);
+        let logs = [
+            log("2021-06-01", 500.),
+            log("2021-06-02", 500.),
+            log("2021-06-03", 100.),
+            log("2021-06-04", 500.),
+        ];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let summary = compute_streak(&habit, &logs, day("2021-06-05"));
+
+        // assert
+        assert_eq!(summary.current, 1);
+        assert_eq!(summary.longest, 2);
+    }
+
+    #[test]
+    fn the_period_in_progress_does_not_break_the_streak() {
+        // arrange
+        let habit = habit(Streak::Daily, 500., Kind::Positive);
+        let logs = [log("2021-06-01", 500.), log("2021-06-02", 500.)];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let summary = compute_streak(&habit, &logs, day("2021-06-03"));
+
+        // assert
+        assert_eq!(summary.current, 2);
+    }
+
+    #[test]
+    fn weekly_habits_add_up_logs_within_an_iso_week() {
+        // arrange
+        let habit = habit(Streak::Weekly, 1000., Kind::Positive);
+        let logs = [
+            // Monday and Sunday of the same ISO week
+            log("2021-05-31", 500.),
+            log("2021-06-06", 500.),
+            // the following week
+            log("2021-06-09", 1000.),
+        ];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let summary = compute_streak(&habit, &logs, day("2021-06-10"));
+
+        // assert
+        assert_eq!(summary.current, 2);
+        assert_eq!(
+            summary.last_completed,
+            Some(Period {
+                start: day("2021-06-07"),
+                end: day("2021-06-13")
+            })
+        );
+    }
+
+    #[test]
+    fn monthly_habits_span_whole_calendar_months() {
+        // arrange
+        let habit = habit(Streak::Monthly, 10., Kind::Positive);
+        let logs = [
+            log("2021-01-31", 10.),
+            log("2021-02-01", 5.),
+            log("2021-02-28", 5.),
+            log("2021-04-15", 10.),
+        ];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let summary = compute_streak(&habit, &logs, day("2021-05-02"));
+
+        // assert
+        assert_eq!(summary.current, 1);
+        assert_eq!(summary.longest, 2);
+    }
+
+    #[test]
+    fn logs_of_other_habits_are_ignored() {
+        // arrange
+        let habit = habit(Streak::Daily, 1., Kind::Positive);
+        let mut other = log("2021-06-01", 1.);
+        other.habit_id = 2;
+        let logs = [&other];
+
+        // act
+        let summary = compute_streak(&habit, &logs, day("2021-06-01"));
+
+        // assert
+        assert_eq!(summary.current, 0);
+        assert_eq!(summary.last_completed, None);
+    }
+
+    #[test]
+    fn negative_habits_are_met_by_staying_at_or_below_the_limit() {
+        // arrange
+        let habit = habit(Streak::Daily, 5., Kind::Negative);
+        let logs = [
+            log("2021-06-01", 3.),
+            log("2021-06-02", 5.),
+            // nothing logged on 2021-06-03, which is a success
+            log("2021-06-04", 2.),
+        ];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let summary = compute_streak(&habit, &logs, day("2021-06-05"));
+
+        // assert
+        assert_eq!(summary.current, 5);
+        assert_eq!(summary.longest, 5);
+    }
+
+    #[test]
+    fn going_over_the_limit_breaks_a_negative_streak_right_away() {
+        // arrange
+        let habit = habit(Streak::Daily, 5., Kind::Negative);
+        let logs = [
+            log("2021-06-01", 3.),
+            log("2021-06-02", 4.),
+            log("2021-06-02", 4.),
+        ];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let summary = compute_streak(&habit, &logs, day("2021-06-02"));
+
+        // assert
+        assert_eq!(summary.current, 0);
+        assert_eq!(summary.longest, 1);
+    }
+
+    #[test]
+    fn negative_habits_count_clean_periods_since_they_were_created() {
+        // arrange
+        let mut habit = habit(Streak::Daily, 5., Kind::Negative);
+        habit.created_at = Some(day("2021-06-01").and_hms(9, 0, 0));
+
+        // act
+        let summary = compute_streak(&habit, &[], day("2021-06-03"));
+
+        // assert
+        assert_eq!(summary.current, 3);
+    }
+
+    #[test]
+    fn paused_days_neither_break_nor_extend_a_streak() {
+        // arrange
+        let mut habit = habit(Streak::Daily, 500., Kind::Positive);
+        habit
+            .pause(day("2021-06-03"), Some(day("2021-06-05")))
+            .expect("Failed to pause habit.");
+        let logs = [
+            log("2021-06-01", 500.),
+            log("2021-06-02", 500.),
+            log("2021-06-06", 500.),
+        ];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let summary = compute_streak(&habit, &logs, day("2021-06-06"));
+
+        // assert
+        assert_eq!(summary.current, 3);
+        assert_eq!(summary.longest, 3);
+    }
+
+    #[test]
+    fn paused_days_do_not_extend_a_negative_streak() {
+        // arrange
+        let mut habit = habit(Streak::Daily, 5., Kind::Negative);
+        habit.created_at = Some(day("2021-06-01").and_hms(9, 0, 0));
+        habit
+            .pause(day("2021-06-03"), Some(day("2021-06-05")))
+            .expect("Failed to pause habit.");
+        // logged within the limit while paused, which still counts
+        let logs = [log("2021-06-04", 2.)];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let summary = compute_streak(&habit, &logs, day("2021-06-06"));
+
+        // assert
+        assert_eq!(summary.current, 4);
+    }
+
+    #[test]
+    fn only_scheduled_days_count_towards_a_streak() {
+        // arrange
+        let mut habit = habit(Streak::Daily, 1., Kind::Positive);
+        habit.schedule = Some(Schedule::Weekdays(vec![
+            Weekday::Mon,
+            Weekday::Wed,
+            Weekday::Fri,
+        ]));
+        // Monday, Wednesday and Friday, with nothing in between
+        let logs = [
+            log("2021-06-07", 1.),
+            log("2021-06-09", 1.),
+            log("2021-06-11", 1.),
+        ];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let summary = compute_streak(&habit, &logs, day("2021-06-13"));
+
+        // assert
+        assert_eq!(summary.current, 3);
+    }
+
+    #[test]
+    fn times_per_week_habits_count_the_days_they_were_met() {
+        // arrange
+        let mut habit = habit(Streak::Weekly, 1., Kind::Positive);
+        habit.schedule = Some(Schedule::TimesPer(3));
+        let logs = [
+            log("2021-06-07", 1.),
+            log("2021-06-08", 1.),
+            log("2021-06-08", 1.),
+            log("2021-06-14", 1.),
+            log("2021-06-16", 1.),
+            log("2021-06-18", 1.),
+        ];
+        let logs: Vec<&HabitLog> = logs.iter().collect();
+
+        // act
+        let summary = compute_streak(&habit, &logs, day("2021-06-20"));
+
+        // assert
+        assert_eq!(summary.current, 1);
+        assert_eq!(summary.last_completed.unwrap().start, day("2021-06-14"));
+    }
+}
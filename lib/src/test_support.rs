@@ -0,0 +1,55 @@
+//! Fixtures shared by the unit tests of the modules
+
+use crate::models::{Habit, HabitDraft, HabitId, HabitLog, HabitName, HabitUnit, Kind, Streak};
+use crate::store::{HabitLogStore, HabitStore};
+use chrono::NaiveDate;
+
+/// The day written as `YYYY-MM-DD`
+pub(crate) fn day(date: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+}
+
+/// Habit 1, writing `quantum` words per `streak`
+pub(crate) fn habit(streak: Streak, quantum: f64, kind: Kind) -> Habit {
+    Habit::new(
+        1,
+        HabitName("writing".into()),
+        quantum,
+        HabitUnit("words".into()),
+        Some(streak),
+        None,
+        Some(kind),
+        None,
+    )
+}
+
+/// A log entry of habit 1, outside of any store
+pub(crate) fn log(date: &str, quantum: f64) -> HabitLog {
+    HabitLog::new_dated(1, quantum, None, day(date))
+}
+
+pub(crate) fn create(store: &mut HabitStore, name: &str, quantum: f64, unit: &str) -> HabitId {
+    store
+        .create(HabitDraft::new(name.into(), quantum, unit.into()))
+        .expect("Failed to create a habit")
+}
+
+/// Log habit `id` once for each date and quantum of `entries`, however long before the habit
+/// was created
+pub(crate) fn add_logs(
+    store: &HabitStore,
+    store_log: &mut HabitLogStore,
+    id: HabitId,
+    entries: &[(&str, f64)],
+) {
+    for (date, quantum) in entries {
+        store_log
+            .add(
+                HabitLog::new_dated(id, *quantum, None, day(date)),
+                store,
+                day(date),
+                true,
+            )
+            .expect("Failed to log the habit");
+    }
+}